@@ -1,26 +1,42 @@
+use rust_rasterizer::core::vector::*;
+use rust_rasterizer::rendering::camera::*;
+use rust_rasterizer::rendering::framebuffer::*;
+use rust_rasterizer::rendering::light::*;
 use rust_rasterizer::rendering::obj::*;
+use rust_rasterizer::rendering::options::*;
+use rust_rasterizer::rendering::shader::*;
 use image::io::Reader as ImageReader;
-use image::{ImageBuffer, Rgb, DynamicImage};
+use image::DynamicImage;
 use std::time::Instant;
-use std::fs::File;
 
 //Width and height of the scene
 const WIDTH: u32 = 1000;
 const HEIGHT: u32 = 1000;
 
 fn main() {
-    //Image where color is stored
-    let mut image = ImageBuffer::<Rgb<u8>, Vec<u8>>::new(WIDTH, HEIGHT);
+    //Frame buffer where color and depth are stored
+    let mut frame = FrameBuffer::new(WIDTH, HEIGHT);
 
-    let file = File::open("src/models/model.obj").unwrap();
-    let mut model = Model::new(file);
+    let mut model = match Model::load("src/models/model.obj") {
+        Ok(model) => model,
+        Err(error) => {
+            eprintln!("{}", error);
+            return;
+        }
+    };
+    for warning in &model.warnings {
+        eprintln!("warning: {}", warning);
+    }
     let img = ImageReader::open("src/models/texture.tga").unwrap().decode().unwrap();
     model.load_texture(img);
 
+    let mut camera = Camera::perspective(Vec3f(0.0, 0.0, 3.0), Vec3f(0.0, 0.0, 0.0), 45.0_f32.to_radians(), WIDTH as f32 / HEIGHT as f32);
+    camera.frame(&model);
+
     println!("Render started...");
     let now = Instant::now();
 
-    render_model(&model, &mut image);
+    render_model(&model, &FlatShader::new(), &camera, &Scene::new(), &RenderOptions::new(), &mut frame);
 
     let duration = now.elapsed();
     println!("Image successfully rendered");
@@ -28,5 +44,5 @@ fn main() {
         "{} milliseconds elapsed.",
         duration.as_secs() * 1000 + u64::from(duration.subsec_millis())
     );
-    DynamicImage::ImageRgb8(image).flipv().save("result.png").unwrap();
+    DynamicImage::ImageRgb8(frame.color).flipv().save("result.png").unwrap();
 }
\ No newline at end of file
@@ -0,0 +1,533 @@
+use crate::core::vector::*;
+use std::ops::*;
+
+//Mat4 is a wrapper for a row major 4x4 array of f32s
+#[derive(Debug, PartialEq, Clone)]
+pub struct Mat4(pub [[f32; 4]; 4]);
+
+impl Mat4 {
+    pub fn identity() -> Mat4 {
+        Mat4([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    //Creates a view matrix for an eye looking at a target, the eye looks down the negative z axis
+    pub fn look_at(eye: &Vec3f, target: &Vec3f, up: &Vec3f) -> Mat4 {
+        let forward = (target - eye).normalize();
+        let right = (&forward * up).normalize();
+        let true_up = &right * &forward;
+        Mat4([
+            [right.0, right.1, right.2, -Vec3f::dot(&right, eye)],
+            [true_up.0, true_up.1, true_up.2, -Vec3f::dot(&true_up, eye)],
+            [-forward.0, -forward.1, -forward.2, Vec3f::dot(&forward, eye)],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    //Creates a perspective projection given a vertical field of view in radians
+    pub fn perspective(fov: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let focal_length = 1.0 / (fov / 2.0).tan();
+        Mat4([
+            [focal_length / aspect, 0.0, 0.0, 0.0],
+            [0.0, focal_length, 0.0, 0.0],
+            [0.0, 0.0, (far + near) / (near - far), (2.0 * far * near) / (near - far)],
+            [0.0, 0.0, -1.0, 0.0],
+        ])
+    }
+
+    //Creates an orthographic projection of a box onto normalized device coordinates
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+        Mat4([
+            [2.0 / (right - left), 0.0, 0.0, -(right + left) / (right - left)],
+            [0.0, 2.0 / (top - bottom), 0.0, -(top + bottom) / (top - bottom)],
+            [0.0, 0.0, -2.0 / (far - near), -(far + near) / (far - near)],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    //Maps normalized device coordinates to the screen, depth is mapped from the near plane at 1 to the far plane at 0
+    pub fn viewport(x: f32, y: f32, width: f32, height: f32) -> Mat4 {
+        Mat4([
+            [width / 2.0, 0.0, 0.0, x + width / 2.0],
+            [0.0, height / 2.0, 0.0, y + height / 2.0],
+            [0.0, 0.0, -0.5, 0.5],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translation(offset: &Vec3f) -> Mat4 {
+        Mat4([
+            [1.0, 0.0, 0.0, offset.0],
+            [0.0, 1.0, 0.0, offset.1],
+            [0.0, 0.0, 1.0, offset.2],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scale(factors: &Vec3f) -> Mat4 {
+        Mat4([
+            [factors.0, 0.0, 0.0, 0.0],
+            [0.0, factors.1, 0.0, 0.0],
+            [0.0, 0.0, factors.2, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    //Creates a rotation of an angle in radians around an axis
+    pub fn rotation(axis: &Vec3f, angle: f32) -> Mat4 {
+        Mat4::from_mat3(&Mat3::rotation(axis, angle))
+    }
+
+    //Creates a rotation from euler angles in radians, rotating around x, then y, then z
+    pub fn rotation_euler(x: f32, y: f32, z: f32) -> Mat4 {
+        Mat4::rotation(&Vec3f(0.0, 0.0, 1.0), z) * Mat4::rotation(&Vec3f(0.0, 1.0, 0.0), y) * Mat4::rotation(&Vec3f(1.0, 0.0, 0.0), x)
+    }
+
+    //Creates a rotation from a quaternion stored as (x, y, z, w)
+    pub fn from_quaternion(quaternion: &Vec4f) -> Mat4 {
+        let length = Vec4f::dot(quaternion, quaternion).sqrt();
+        let Vec4f(x, y, z, w) = quaternion * (1.0 / length);
+        Mat4([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w), 0.0],
+            [2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w), 0.0],
+            [2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    //Embeds a Mat3 in the upper left corner of an identity matrix
+    pub fn from_mat3(matrix: &Mat3) -> Mat4 {
+        let mut result = Mat4::identity();
+        for row in 0..3 {
+            for column in 0..3 {
+                result.0[row][column] = matrix.0[row][column];
+            }
+        }
+        result
+    }
+
+    //Takes the upper left 3x3 corner of the matrix
+    pub fn to_mat3(&self) -> Mat3 {
+        let mut result = Mat3::identity();
+        for row in 0..3 {
+            for column in 0..3 {
+                result.0[row][column] = self.0[row][column];
+            }
+        }
+        result
+    }
+
+    //Finds the matrix which transforms normals, the inverse transpose of the upper 3x3 corner
+    pub fn normal_matrix(&self) -> Mat3 {
+        self.to_mat3().inverse().unwrap_or_else(Mat3::identity).transpose()
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut result = [[0.0; 4]; 4];
+        for (row, result_row) in result.iter_mut().enumerate() {
+            for (column, value) in result_row.iter_mut().enumerate() {
+                *value = self.0[column][row];
+            }
+        }
+        Mat4(result)
+    }
+
+    //Finds the 3x3 matrix left after removing a row and a column
+    fn minor(&self, row: usize, column: usize) -> Mat3 {
+        let mut result = [[0.0; 3]; 3];
+        let rows = (0..4).filter(|index| *index != row);
+        for (result_row, source_row) in result.iter_mut().zip(rows) {
+            let columns = (0..4).filter(|index| *index != column);
+            for (value, source_column) in result_row.iter_mut().zip(columns) {
+                *value = self.0[source_row][source_column];
+            }
+        }
+        Mat3(result)
+    }
+
+    //Finds the cofactor of an element of the matrix
+    fn cofactor(&self, row: usize, column: usize) -> f32 {
+        let determinant = self.minor(row, column).determinant();
+        //Elements whose row and column add up to an even number keep the sign of their minor
+        if (row + column) & 1 == 0 {
+            determinant
+        }
+        else {
+            -determinant
+        }
+    }
+
+    pub fn determinant(&self) -> f32 {
+        (0..4).map(|column| self.0[0][column] * self.cofactor(0, column)).sum()
+    }
+
+    //Inverts the matrix, returning None if it is singular
+    pub fn inverse(&self) -> Option<Mat4> {
+        let determinant = self.determinant();
+        if determinant.abs() < f32::EPSILON * f32::EPSILON {
+            return None;
+        }
+        let mut result = [[0.0; 4]; 4];
+        for (row, result_row) in result.iter_mut().enumerate() {
+            for (column, value) in result_row.iter_mut().enumerate() {
+                //The adjugate is the transpose of the cofactor matrix
+                *value = self.cofactor(column, row) / determinant;
+            }
+        }
+        Some(Mat4(result))
+    }
+
+    //Transforms a point, dividing by w when the matrix is projective
+    pub fn transform_point(&self, point: &Vec3f) -> Vec3f {
+        (self * Vec4f::point(point)).perspective_divide()
+    }
+
+    //Transforms a direction, ignoring translation
+    pub fn transform_direction(&self, direction: &Vec3f) -> Vec3f {
+        (self * Vec4f::direction(direction)).xyz()
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        self.0[row][column]
+    }
+
+    pub fn set(&mut self, row: usize, column: usize, value: f32) {
+        self.0[row][column] = value;
+    }
+}
+
+//Multiplies two matrices
+fn multiply(mat1: &Mat4, mat2: &Mat4) -> Mat4 {
+    let mut result = [[0.0; 4]; 4];
+    for (row, result_row) in result.iter_mut().enumerate() {
+        for (column, value) in result_row.iter_mut().enumerate() {
+            *value = (0..4).map(|index| mat1.0[row][index] * mat2.0[index][column]).sum();
+        }
+    }
+    Mat4(result)
+}
+
+//Multiplies a matrix and a column vector
+fn transform(matrix: &Mat4, vector: &Vec4f) -> Vec4f {
+    let row = |index: usize| {
+        let values = &matrix.0[index];
+        values[0] * vector.0 + values[1] * vector.1 + values[2] * vector.2 + values[3] * vector.3
+    };
+    Vec4f(row(0), row(1), row(2), row(3))
+}
+
+//Mat4 * Mat4
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        multiply(&self, &other)
+    }
+}
+//&Mat4 * &Mat4
+impl Mul<&Mat4> for &Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: &Mat4) -> Mat4 {
+        multiply(self, other)
+    }
+}
+//&Mat4 * Mat4
+impl Mul<Mat4> for &Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        multiply(self, &other)
+    }
+}
+//Mat4 * &Mat4
+impl Mul<&Mat4> for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: &Mat4) -> Mat4 {
+        multiply(&self, other)
+    }
+}
+
+//Mat4 * Vec4f
+impl Mul<Vec4f> for Mat4 {
+    type Output = Vec4f;
+
+    fn mul(self, other: Vec4f) -> Vec4f {
+        transform(&self, &other)
+    }
+}
+//&Mat4 * &Vec4f
+impl Mul<&Vec4f> for &Mat4 {
+    type Output = Vec4f;
+
+    fn mul(self, other: &Vec4f) -> Vec4f {
+        transform(self, other)
+    }
+}
+//&Mat4 * Vec4f
+impl Mul<Vec4f> for &Mat4 {
+    type Output = Vec4f;
+
+    fn mul(self, other: Vec4f) -> Vec4f {
+        transform(self, &other)
+    }
+}
+//Mat4 * &Vec4f
+impl Mul<&Vec4f> for Mat4 {
+    type Output = Vec4f;
+
+    fn mul(self, other: &Vec4f) -> Vec4f {
+        transform(&self, other)
+    }
+}
+
+//Mat3 is a wrapper for a row major 3x3 array of f32s
+#[derive(Debug, PartialEq, Clone)]
+pub struct Mat3(pub [[f32; 3]; 3]);
+
+impl Mat3 {
+    pub fn identity() -> Mat3 {
+        Mat3([
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+        ])
+    }
+
+    //Creates a matrix whose columns are the given vectors
+    pub fn from_columns(column0: &Vec3f, column1: &Vec3f, column2: &Vec3f) -> Mat3 {
+        Mat3([
+            [column0.0, column1.0, column2.0],
+            [column0.1, column1.1, column2.1],
+            [column0.2, column1.2, column2.2],
+        ])
+    }
+
+    //Creates a rotation of an angle in radians around an axis
+    pub fn rotation(axis: &Vec3f, angle: f32) -> Mat3 {
+        let Vec3f(x, y, z) = axis.normalize();
+        let (sin, cos) = angle.sin_cos();
+        let t = 1.0 - cos;
+        Mat3([
+            [t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y],
+            [t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x],
+            [t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos],
+        ])
+    }
+
+    pub fn transpose(&self) -> Mat3 {
+        let mut result = [[0.0; 3]; 3];
+        for (row, result_row) in result.iter_mut().enumerate() {
+            for (column, value) in result_row.iter_mut().enumerate() {
+                *value = self.0[column][row];
+            }
+        }
+        Mat3(result)
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let m = &self.0;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    //Inverts the matrix, returning None if it is singular
+    pub fn inverse(&self) -> Option<Mat3> {
+        let determinant = self.determinant();
+        if determinant.abs() < f32::EPSILON * f32::EPSILON {
+            return None;
+        }
+        let m = &self.0;
+        let inverse_determinant = 1.0 / determinant;
+        Some(Mat3([
+            [
+                (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * inverse_determinant,
+                (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * inverse_determinant,
+                (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * inverse_determinant,
+            ],
+            [
+                (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * inverse_determinant,
+                (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * inverse_determinant,
+                (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * inverse_determinant,
+            ],
+            [
+                (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * inverse_determinant,
+                (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * inverse_determinant,
+                (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * inverse_determinant,
+            ],
+        ]))
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        self.0[row][column]
+    }
+
+    pub fn set(&mut self, row: usize, column: usize, value: f32) {
+        self.0[row][column] = value;
+    }
+}
+
+//Multiplies two matrices
+fn multiply3(mat1: &Mat3, mat2: &Mat3) -> Mat3 {
+    let mut result = [[0.0; 3]; 3];
+    for (row, result_row) in result.iter_mut().enumerate() {
+        for (column, value) in result_row.iter_mut().enumerate() {
+            *value = (0..3).map(|index| mat1.0[row][index] * mat2.0[index][column]).sum();
+        }
+    }
+    Mat3(result)
+}
+
+//Multiplies a matrix and a column vector
+fn transform3(matrix: &Mat3, vector: &Vec3f) -> Vec3f {
+    let row = |index: usize| {
+        let values = &matrix.0[index];
+        values[0] * vector.0 + values[1] * vector.1 + values[2] * vector.2
+    };
+    Vec3f(row(0), row(1), row(2))
+}
+//Mat3 * Mat3
+impl Mul for Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, other: Mat3) -> Mat3 {
+        multiply3(&self, &other)
+    }
+}
+//&Mat3 * &Mat3
+impl Mul<&Mat3> for &Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, other: &Mat3) -> Mat3 {
+        multiply3(self, other)
+    }
+}
+//&Mat3 * Mat3
+impl Mul<Mat3> for &Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, other: Mat3) -> Mat3 {
+        multiply3(self, &other)
+    }
+}
+//Mat3 * &Mat3
+impl Mul<&Mat3> for Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, other: &Mat3) -> Mat3 {
+        multiply3(&self, other)
+    }
+}
+
+//Mat3 * Vec3f
+impl Mul<Vec3f> for Mat3 {
+    type Output = Vec3f;
+
+    fn mul(self, other: Vec3f) -> Vec3f {
+        transform3(&self, &other)
+    }
+}
+//&Mat3 * &Vec3f
+impl Mul<&Vec3f> for &Mat3 {
+    type Output = Vec3f;
+
+    fn mul(self, other: &Vec3f) -> Vec3f {
+        transform3(self, other)
+    }
+}
+//&Mat3 * Vec3f
+impl Mul<Vec3f> for &Mat3 {
+    type Output = Vec3f;
+
+    fn mul(self, other: Vec3f) -> Vec3f {
+        transform3(self, &other)
+    }
+}
+//Mat3 * &Vec3f
+impl Mul<&Vec3f> for Mat3 {
+    type Output = Vec3f;
+
+    fn mul(self, other: &Vec3f) -> Vec3f {
+        transform3(&self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Checks that every element of two matrices is within a small tolerance
+    fn assert_mat4_near(actual: &Mat4, expected: &Mat4) {
+        for row in 0..4 {
+            for column in 0..4 {
+                assert!((actual.get(row, column) - expected.get(row, column)).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+            }
+        }
+    }
+
+    fn assert_mat3_near(actual: &Mat3, expected: &Mat3) {
+        for row in 0..3 {
+            for column in 0..3 {
+                assert!((actual.get(row, column) - expected.get(row, column)).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+            }
+        }
+    }
+
+    fn model_transform() -> Mat4 {
+        Mat4::translation(&Vec3f(1.0, -2.0, 3.0)) * Mat4::rotation_euler(0.3, -0.7, 1.1) * Mat4::scale(&Vec3f(2.0, 0.5, 3.0))
+    }
+
+    #[test]
+    fn mat4_inverse_round_trips() {
+        let matrix = model_transform();
+        let inverse = matrix.inverse().unwrap();
+        assert_mat4_near(&(&matrix * &inverse), &Mat4::identity());
+        assert_mat4_near(&(&inverse * &matrix), &Mat4::identity());
+
+        let point = Vec3f(0.5, 4.0, -1.5);
+        let round_trip = inverse.transform_point(&matrix.transform_point(&point));
+        assert!(Vec3f::magnitude(&(round_trip - point)) < 1e-4);
+    }
+
+    #[test]
+    fn mat4_inverse_of_projection_round_trips() {
+        let matrix = Mat4::perspective(1.0, 1.5, 0.1, 100.0) * Mat4::look_at(&Vec3f(1.0, 2.0, 5.0), &Vec3f(0.0, 0.0, 0.0), &Vec3f(0.0, 1.0, 0.0));
+        assert_mat4_near(&(&matrix * matrix.inverse().unwrap()), &Mat4::identity());
+    }
+
+    #[test]
+    fn singular_matrices_have_no_inverse() {
+        assert!(Mat4::scale(&Vec3f(1.0, 0.0, 1.0)).inverse().is_none());
+        assert!(Mat3([[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 1.0, 0.0]]).inverse().is_none());
+    }
+
+    #[test]
+    fn mat3_inverse_round_trips() {
+        let matrix = model_transform().to_mat3();
+        assert_mat3_near(&(&matrix * matrix.inverse().unwrap()), &Mat3::identity());
+    }
+
+    #[test]
+    fn normal_matrix_of_rotation_is_the_rotation() {
+        let rotation = Mat4::rotation(&Vec3f(1.0, 2.0, -1.0).normalize(), 0.8);
+        assert_mat3_near(&rotation.normal_matrix(), &rotation.to_mat3());
+    }
+
+    #[test]
+    fn normal_matrix_keeps_normals_perpendicular_under_non_uniform_scale() {
+        let matrix = model_transform();
+        let normal = Vec3f(1.0, 1.0, 0.0).normalize();
+        let tangent = Vec3f(1.0, -1.0, 0.5);
+        let transformed_normal = matrix.normal_matrix() * &normal;
+        let transformed_tangent = matrix.transform_direction(&tangent);
+        assert!(Vec3f::dot(&transformed_normal, &transformed_tangent).abs() < 1e-4);
+        //The inverse transpose undoes itself
+        assert_mat3_near(&matrix.inverse().unwrap().normal_matrix(), &matrix.to_mat3().transpose());
+    }
+}
\ No newline at end of file
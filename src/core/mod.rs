@@ -1 +1,2 @@
+pub mod matrix;
 pub mod vector;
\ No newline at end of file
@@ -17,7 +17,7 @@ impl Vec3f {
 
     //Normalizes a Vec3f
     pub fn normalize(&self) -> Vec3f {
-        let magnitude = Vec3f::magnitude(&self);
+        let magnitude = Vec3f::magnitude(self);
         Vec3f(self.0 / magnitude, self.1 / magnitude, self.2 / magnitude)
     }
 
@@ -26,6 +26,11 @@ impl Vec3f {
         (vec1.0 * vec2.0) + (vec1.1 * vec2.1) + (vec1.2 * vec2.2)
     }
 
+    //Multiplies 2 Vec3f component by component
+    pub fn multiply(vec1: &Vec3f, vec2: &Vec3f) -> Vec3f {
+        Vec3f(vec1.0 * vec2.0, vec1.1 * vec2.1, vec1.2 * vec2.2)
+    }
+
     //Reflects a vector about a given normal
     pub fn reflect(vector: &Vec3f, normal: &Vec3f) -> Vec3f {
         vector - (normal * 2.0 * Vec3f::dot(vector, normal))
@@ -60,15 +65,15 @@ impl Add for Vec3f {
     }
 }
 //&Vec3f + &Vec4
-impl<'a, 'b> Add<&'b Vec3f> for &'a Vec3f {
+impl Add<&Vec3f> for &Vec3f {
     type Output = Vec3f;
     
-    fn add(self, other: &'b Vec3f) -> Vec3f {
+    fn add(self, other: &Vec3f) -> Vec3f {
         Vec3f(self.0 + other.0, self.1 + other.1, self.2 + other.2)
     }
 }
 //&Vec3f + Vec4
-impl<'a> Add<Vec3f> for &'a Vec3f {
+impl Add<Vec3f> for &Vec3f {
     type Output = Vec3f;
     
     fn add(self, other: Vec3f) -> Vec3f {
@@ -76,10 +81,10 @@ impl<'a> Add<Vec3f> for &'a Vec3f {
     }
 }
 //Vec3f + &Vec4
-impl<'a> Add<&'a Vec3f> for Vec3f {
+impl Add<&Vec3f> for Vec3f {
     type Output = Vec3f;
     
-    fn add(self, other: &'a Vec3f) -> Vec3f {
+    fn add(self, other: &Vec3f) -> Vec3f {
         Vec3f(self.0 + other.0, self.1 + other.1, self.2 + other.2)
     }
 }
@@ -93,15 +98,15 @@ impl Sub for Vec3f {
     }
 }
 //&Vec3f - &Vec4s
-impl<'a, 'b> Sub<&'b Vec3f> for &'a Vec3f {
+impl Sub<&Vec3f> for &Vec3f {
     type Output = Vec3f;
     
-    fn sub(self, other: &'b Vec3f) -> Vec3f {
+    fn sub(self, other: &Vec3f) -> Vec3f {
         Vec3f(self.0 - other.0, self.1 - other.1, self.2 - other.2)
     }
 }
 //&Vec3f - Vec4
-impl<'a> Sub<Vec3f> for &'a Vec3f {
+impl Sub<Vec3f> for &Vec3f {
     type Output = Vec3f;
     
     fn sub(self, other: Vec3f) -> Vec3f {
@@ -109,10 +114,10 @@ impl<'a> Sub<Vec3f> for &'a Vec3f {
     }
 }
 //Vec3f - &Vec4
-impl<'a> Sub<&'a Vec3f> for Vec3f {
+impl Sub<&Vec3f> for Vec3f {
     type Output = Vec3f;
     
-    fn sub(self, other: &'a Vec3f) -> Vec3f {
+    fn sub(self, other: &Vec3f) -> Vec3f {
         Vec3f(self.0 - other.0, self.1 - other.1, self.2 - other.2)
     }
 }
@@ -126,15 +131,15 @@ impl Mul<f32> for Vec3f {
     }
 }
 //&Vec3f * &f32
-impl<'a, 'b> Mul<&'b f32> for &'a Vec3f {
+impl Mul<&f32> for &Vec3f {
     type Output = Vec3f;
     
-    fn mul(self, other: &'b f32) -> Vec3f {
+    fn mul(self, other: &f32) -> Vec3f {
         Vec3f(self.0 * other, self.1 * other, self.2 * other)
     }
 }
 //&Vec3f * f32
-impl<'a> Mul<f32> for &'a Vec3f {
+impl Mul<f32> for &Vec3f {
     type Output = Vec3f;
     
     fn mul(self, other: f32) -> Vec3f {
@@ -142,10 +147,10 @@ impl<'a> Mul<f32> for &'a Vec3f {
     }
 }
 //Vec3f * &f32
-impl<'a> Mul<&'a f32> for Vec3f {
+impl Mul<&f32> for Vec3f {
     type Output = Vec3f;
     
-    fn mul(self, other: &'a f32) -> Vec3f {
+    fn mul(self, other: &f32) -> Vec3f {
         Vec3f(self.0 * other, self.1 * other, self.2 * other)
     }
 }
@@ -159,15 +164,15 @@ impl Mul<Vec3f> for f32 {
     }
 }
 //&f32 * &Vec3f
-impl<'a, 'b> Mul<&'b Vec3f> for &'a f32 {
+impl Mul<&Vec3f> for &f32 {
     type Output = Vec3f;
     
-    fn mul(self, other: &'b Vec3f) -> Vec3f {
+    fn mul(self, other: &Vec3f) -> Vec3f {
         Vec3f(other.0 * self, other.1 * self, other.2 * self)
     }
 }
 //&f32 * &Vec3f
-impl<'a> Mul<Vec3f> for &'a f32 {
+impl Mul<Vec3f> for &f32 {
     type Output = Vec3f;
     
     fn mul(self, other: Vec3f) -> Vec3f {
@@ -175,10 +180,10 @@ impl<'a> Mul<Vec3f> for &'a f32 {
     }
 }
 //f32 * &Vec3f
-impl<'a> Mul<&'a Vec3f> for f32 {
+impl Mul<&Vec3f> for f32 {
     type Output = Vec3f;
     
-    fn mul(self, other: &'a Vec3f) -> Vec3f {
+    fn mul(self, other: &Vec3f) -> Vec3f {
         Vec3f(other.0 * self, other.1 * self, other.2 * self)
     }
 }
@@ -192,15 +197,15 @@ impl Mul for Vec3f {
     }
 }
 //&Vec3f * &Vec3
-impl<'a, 'b> Mul<&'b Vec3f> for &'a Vec3f {
+impl Mul<&Vec3f> for &Vec3f {
     type Output = Vec3f;
     
-    fn mul(self, other: &'b Vec3f) -> Vec3f {
+    fn mul(self, other: &Vec3f) -> Vec3f {
         Vec3f((self.1 * other.2) - (self.2 * other.1), (self.2 * other.0) - (self.0 * other.2), (self.0 * other.1) - (self.1 * other.0))
     }
 }
 //&Vec3f * Vec3
-impl<'a> Mul<Vec3f> for &'a Vec3f {
+impl Mul<Vec3f> for &Vec3f {
     type Output = Vec3f;
     
     fn mul(self, other: Vec3f) -> Vec3f {
@@ -208,19 +213,207 @@ impl<'a> Mul<Vec3f> for &'a Vec3f {
     }
 }
 //Vec3f * &Vec3
-impl<'a> Mul<&'a Vec3f> for Vec3f {
+impl Mul<&Vec3f> for Vec3f {
     type Output = Vec3f;
     
-    fn mul(self, other: &'a Vec3f) -> Vec3f {
+    fn mul(self, other: &Vec3f) -> Vec3f {
         Vec3f((self.1 * other.2) - (self.2 * other.1), (self.2 * other.0) - (self.0 * other.2), (self.0 * other.1) - (self.1 * other.0))
     }
 }
 
+//Vec4f is a wrapper for a Tuple of 4 f32s, used for homogeneous coordinates
+#[derive(Debug, PartialEq, Clone)]
+pub struct Vec4f(pub f32, pub f32, pub f32, pub f32);
+
+impl Vec4f {
+    //Creates a homogeneous point from a Vec3f
+    pub fn point(vector: &Vec3f) -> Vec4f {
+        Vec4f(vector.0, vector.1, vector.2, 1.0)
+    }
+
+    //Creates a homogeneous direction from a Vec3f
+    pub fn direction(vector: &Vec3f) -> Vec4f {
+        Vec4f(vector.0, vector.1, vector.2, 0.0)
+    }
+
+    //Drops the w component of a Vec4f
+    pub fn xyz(&self) -> Vec3f {
+        Vec3f(self.0, self.1, self.2)
+    }
+
+    //Divides the x, y and z components by w
+    pub fn perspective_divide(&self) -> Vec3f {
+        Vec3f(self.0 / self.3, self.1 / self.3, self.2 / self.3)
+    }
+
+    //Finds the dot product of 2 Vec4f
+    pub fn dot(vec1: &Vec4f, vec2: &Vec4f) -> f32 {
+        (vec1.0 * vec2.0) + (vec1.1 * vec2.1) + (vec1.2 * vec2.2) + (vec1.3 * vec2.3)
+    }
+
+    pub fn get(&self, index: usize) -> f32 {
+        match index {
+            0 => self.0,
+            1 => self.1,
+            2 => self.2,
+            3 => self.3,
+            _ => panic!("Index is out of Vec4 range"),
+        }
+    }
+
+    pub fn set(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.0 = value,
+            1 => self.1 = value,
+            2 => self.2 = value,
+            3 => self.3 = value,
+            _ => panic!("Index is out of Vec4 range"),
+        }
+    }
+}
+
+//Vec4f + Vec4f
+impl Add for Vec4f {
+    type Output = Vec4f;
+
+    fn add(self, other: Vec4f) -> Vec4f {
+        Vec4f(self.0 + other.0, self.1 + other.1, self.2 + other.2, self.3 + other.3)
+    }
+}
+//&Vec4f + &Vec4f
+impl Add<&Vec4f> for &Vec4f {
+    type Output = Vec4f;
+
+    fn add(self, other: &Vec4f) -> Vec4f {
+        Vec4f(self.0 + other.0, self.1 + other.1, self.2 + other.2, self.3 + other.3)
+    }
+}
+//&Vec4f + Vec4f
+impl Add<Vec4f> for &Vec4f {
+    type Output = Vec4f;
+
+    fn add(self, other: Vec4f) -> Vec4f {
+        Vec4f(self.0 + other.0, self.1 + other.1, self.2 + other.2, self.3 + other.3)
+    }
+}
+//Vec4f + &Vec4f
+impl Add<&Vec4f> for Vec4f {
+    type Output = Vec4f;
+
+    fn add(self, other: &Vec4f) -> Vec4f {
+        Vec4f(self.0 + other.0, self.1 + other.1, self.2 + other.2, self.3 + other.3)
+    }
+}
+
+//Vec4f - Vec4f
+impl Sub for Vec4f {
+    type Output = Vec4f;
+
+    fn sub(self, other: Vec4f) -> Vec4f {
+        Vec4f(self.0 - other.0, self.1 - other.1, self.2 - other.2, self.3 - other.3)
+    }
+}
+//&Vec4f - &Vec4f
+impl Sub<&Vec4f> for &Vec4f {
+    type Output = Vec4f;
+
+    fn sub(self, other: &Vec4f) -> Vec4f {
+        Vec4f(self.0 - other.0, self.1 - other.1, self.2 - other.2, self.3 - other.3)
+    }
+}
+//&Vec4f - Vec4f
+impl Sub<Vec4f> for &Vec4f {
+    type Output = Vec4f;
+
+    fn sub(self, other: Vec4f) -> Vec4f {
+        Vec4f(self.0 - other.0, self.1 - other.1, self.2 - other.2, self.3 - other.3)
+    }
+}
+//Vec4f - &Vec4f
+impl Sub<&Vec4f> for Vec4f {
+    type Output = Vec4f;
+
+    fn sub(self, other: &Vec4f) -> Vec4f {
+        Vec4f(self.0 - other.0, self.1 - other.1, self.2 - other.2, self.3 - other.3)
+    }
+}
+
+//Vec4f * f32
+impl Mul<f32> for Vec4f {
+    type Output = Vec4f;
+
+    fn mul(self, other: f32) -> Vec4f {
+        Vec4f(self.0 * other, self.1 * other, self.2 * other, self.3 * other)
+    }
+}
+//&Vec4f * &f32
+impl Mul<&f32> for &Vec4f {
+    type Output = Vec4f;
+
+    fn mul(self, other: &f32) -> Vec4f {
+        Vec4f(self.0 * other, self.1 * other, self.2 * other, self.3 * other)
+    }
+}
+//&Vec4f * f32
+impl Mul<f32> for &Vec4f {
+    type Output = Vec4f;
+
+    fn mul(self, other: f32) -> Vec4f {
+        Vec4f(self.0 * other, self.1 * other, self.2 * other, self.3 * other)
+    }
+}
+//Vec4f * &f32
+impl Mul<&f32> for Vec4f {
+    type Output = Vec4f;
+
+    fn mul(self, other: &f32) -> Vec4f {
+        Vec4f(self.0 * other, self.1 * other, self.2 * other, self.3 * other)
+    }
+}
+
+//f32 * Vec4f
+impl Mul<Vec4f> for f32 {
+    type Output = Vec4f;
+
+    fn mul(self, other: Vec4f) -> Vec4f {
+        Vec4f(other.0 * self, other.1 * self, other.2 * self, other.3 * self)
+    }
+}
+//&f32 * &Vec4f
+impl Mul<&Vec4f> for &f32 {
+    type Output = Vec4f;
+
+    fn mul(self, other: &Vec4f) -> Vec4f {
+        Vec4f(other.0 * self, other.1 * self, other.2 * self, other.3 * self)
+    }
+}
+//&f32 * Vec4f
+impl Mul<Vec4f> for &f32 {
+    type Output = Vec4f;
+
+    fn mul(self, other: Vec4f) -> Vec4f {
+        Vec4f(other.0 * self, other.1 * self, other.2 * self, other.3 * self)
+    }
+}
+//f32 * &Vec4f
+impl Mul<&Vec4f> for f32 {
+    type Output = Vec4f;
+
+    fn mul(self, other: &Vec4f) -> Vec4f {
+        Vec4f(other.0 * self, other.1 * self, other.2 * self, other.3 * self)
+    }
+}
+
 //Vec2 is a wrapper for a Tuple of 2 f32s
 #[derive(Debug, PartialEq, Clone)]
 pub struct Vec2f(pub f32, pub f32);
 
 impl Vec2f {
+    //Gets the magnitude of a Vec2f
+    pub fn magnitude(vector: &Vec2f) -> f32 {
+        ((vector.0 * vector.0) + (vector.1 * vector.1)).sqrt()
+    }
+
     pub fn get(&self, index: usize) -> f32 {
         match index {
             0 => self.0,
@@ -246,6 +439,14 @@ impl Add for Vec2f {
         Vec2f(self.0 + other.0, self.1 + other.1)
     }
 }
+//&Vec2f + Vec2f
+impl Add<Vec2f> for &Vec2f {
+    type Output = Vec2f;
+    
+    fn add(self, other: Vec2f) -> Vec2f {
+        Vec2f(self.0 + other.0, self.1 + other.1)
+    }
+}
 
 //Vec2f * f32
 impl Mul<f32> for Vec2f {
@@ -256,15 +457,15 @@ impl Mul<f32> for Vec2f {
     }
 }
 //&Vec3f * &f32
-impl<'a, 'b> Mul<&'b f32> for &'a Vec2f {
+impl Mul<&f32> for &Vec2f {
     type Output = Vec2f;
     
-    fn mul(self, other: &'b f32) -> Vec2f {
+    fn mul(self, other: &f32) -> Vec2f {
         Vec2f(self.0 * other, self.1 * other)
     }
 }
 //&Vec3f * f32
-impl<'a> Mul<f32> for &'a Vec2f {
+impl Mul<f32> for &Vec2f {
     type Output = Vec2f;
     
     fn mul(self, other: f32) -> Vec2f {
@@ -272,10 +473,10 @@ impl<'a> Mul<f32> for &'a Vec2f {
     }
 }
 //Vec3f * &f32
-impl<'a> Mul<&'a f32> for Vec2f {
+impl Mul<&f32> for Vec2f {
     type Output = Vec2f;
     
-    fn mul(self, other: &'a f32) -> Vec2f {
+    fn mul(self, other: &f32) -> Vec2f {
         Vec2f(self.0 * other, self.1 * other)
     }
 }
@@ -289,15 +490,15 @@ impl Mul<Vec2f> for f32 {
     }
 }
 //&f32 * &Vec3f
-impl<'a, 'b> Mul<&'b Vec2f> for &'a f32 {
+impl Mul<&Vec2f> for &f32 {
     type Output = Vec2f;
     
-    fn mul(self, other: &'b Vec2f) -> Vec2f {
+    fn mul(self, other: &Vec2f) -> Vec2f {
         Vec2f(other.0 * self, other.1 * self)
     }
 }
 //&f32 * &Vec3f
-impl<'a> Mul<Vec2f> for &'a f32 {
+impl Mul<Vec2f> for &f32 {
     type Output = Vec2f;
     
     fn mul(self, other: Vec2f) -> Vec2f {
@@ -305,10 +506,10 @@ impl<'a> Mul<Vec2f> for &'a f32 {
     }
 }
 //f32 * &Vec3f
-impl<'a> Mul<&'a Vec2f> for f32 {
+impl Mul<&Vec2f> for f32 {
     type Output = Vec2f;
     
-    fn mul(self, other: &'a Vec2f) -> Vec2f {
+    fn mul(self, other: &Vec2f) -> Vec2f {
         Vec2f(other.0 * self, other.1 * self)
     }
 }
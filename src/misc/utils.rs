@@ -1,3 +1,7 @@
+use crate::core::vector::*;
+use image::DynamicImage;
+use std::ops::{Add, Mul};
+
 //Clamps a given float
 pub fn clamp_float(num: f32, min: f32, max: f32) -> f32 {
     if num > max {
@@ -29,4 +33,78 @@ pub fn min_float(num1: f32, num2: f32) -> f32 {
     else {
         num2
     }
+}
+
+//Converts a color from 0 to 1 into an 8 bit color
+pub fn to_rgb(color: &Vec3f) -> [u8; 3] {
+    let channel = |value: f32| (clamp_float(value, 0.0, 1.0) * 255.0).round() as u8;
+    [channel(color.0), channel(color.1), channel(color.2)]
+}
+
+//Converts a channel from the sRGB curve images are stored with to linear light
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    }
+    else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+//Converts a channel from linear light to the sRGB curve images are displayed with
+pub fn linear_to_srgb(value: f32) -> f32 {
+    let value = clamp_float(value, 0.0, 1.0);
+    if value <= 0.0031308 {
+        value * 12.92
+    }
+    else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+//Finds an up vector which is not parallel to a direction
+pub fn up_vector(direction: &Vec3f) -> Vec3f {
+    if direction.1.abs() > 0.99 { Vec3f(0.0, 0.0, 1.0) } else { Vec3f(0.0, 1.0, 0.0) }
+}
+
+//Converts the pixels of an image to linear RGB, top to bottom, floating point images such as HDR and OpenEXR already hold linear light while others are stored with the sRGB curve
+pub fn linear_pixels(image: &DynamicImage) -> Vec<Vec3f> {
+    let is_linear = matches!(image, DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_));
+    let channel = |value: f32| if is_linear { value } else { srgb_to_linear(value) };
+    image.to_rgb32f().pixels().map(|pixel| Vec3f(channel(pixel[0]), channel(pixel[1]), channel(pixel[2]))).collect()
+}
+
+//Blends the four pixels closest to a position in pixels, where the centers of pixels lie on whole numbers, fetch handles positions past the edges
+pub fn bilinear<T, F>(x: f32, y: f32, fetch: F) -> T
+where
+    T: Add<Output = T> + Mul<f32, Output = T>,
+    F: Fn(i64, i64) -> T,
+{
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let first = fetch(x0, y0) * (1.0 - tx) + fetch(x0 + 1, y0) * tx;
+    let second = fetch(x0, y0 + 1) * (1.0 - tx) + fetch(x0 + 1, y0 + 1) * tx;
+    first * (1.0 - ty) + second * ty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    #[test]
+    fn float_images_keep_their_linear_range() {
+        let image = ImageBuffer::<Rgb<f32>, Vec<f32>>::from_raw(2, 1, vec![0.5, 2.0, 16.0, 0.0, 0.25, 1.0]).unwrap();
+        assert_eq!(linear_pixels(&DynamicImage::ImageRgb32F(image)), vec![Vec3f(0.5, 2.0, 16.0), Vec3f(0.0, 0.25, 1.0)]);
+    }
+
+    #[test]
+    fn eight_bit_images_are_converted_from_srgb() {
+        let image = ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(1, 1, vec![0, 188, 255]).unwrap();
+        let pixels = linear_pixels(&DynamicImage::ImageRgb8(image));
+        assert_eq!(pixels[0].0, 0.0);
+        assert!((pixels[0].1 - 0.5).abs() < 0.01, "{:?}", pixels);
+        assert!((pixels[0].2 - 1.0).abs() < 1e-6);
+    }
 }
\ No newline at end of file
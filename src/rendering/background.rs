@@ -0,0 +1,193 @@
+use crate::core::vector::*;
+use crate::misc::utils::*;
+use crate::rendering::camera::*;
+use crate::rendering::environment::*;
+use crate::rendering::framebuffer::*;
+use crate::rendering::texture::*;
+use image::DynamicImage;
+use std::path::Path;
+
+//One square face of a cube map, stored top to bottom
+pub struct CubeFace {
+    pub width: usize,
+    pub height: usize,
+    //Linear RGB radiance
+    pub pixels: Vec<Vec3f>,
+}
+
+impl CubeFace {
+    pub fn new(image: &DynamicImage) -> CubeFace {
+        CubeFace {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels: linear_pixels(image),
+        }
+    }
+
+    //Finds the color at coordinates from 0 to 1 across the face, with t pointing down, blending the four closest pixels
+    fn sample(&self, s: f32, t: f32) -> Vec3f {
+        //Pixels past the edges of a face are clamped to it
+        bilinear(s * self.width as f32 - 0.5, t * self.height as f32 - 0.5, |x, y| {
+            self.pixels[x.clamp(0, self.width as i64 - 1) as usize + y.clamp(0, self.height as i64 - 1) as usize * self.width].clone()
+        })
+    }
+}
+
+//Stores the surroundings of a scene as six images on the faces of a cube, in the order +x, -x, +y, -y, +z, -z
+pub struct CubeMap {
+    pub faces: Vec<CubeFace>,
+}
+
+impl CubeMap {
+    pub fn new(images: &[DynamicImage; 6]) -> CubeMap {
+        CubeMap {
+            faces: images.iter().map(CubeFace::new).collect(),
+        }
+    }
+
+    //Loads the six faces of a cube map from image files, in the order +x, -x, +y, -y, +z, -z
+    pub fn load<P: AsRef<Path>>(paths: &[P; 6]) -> Result<CubeMap, ImageLoadError> {
+        let mut faces = Vec::with_capacity(6);
+        for path in paths {
+            let path = path.as_ref();
+            match image::open(path) {
+                Ok(image) => faces.push(CubeFace::new(&image)),
+                Err(error) => return Err(ImageLoadError::new(path, error)),
+            }
+        }
+        Ok(CubeMap { faces })
+    }
+
+    //Finds the radiance seen looking towards a direction, picking the face by the largest component of the direction
+    pub fn sample(&self, direction: &Vec3f) -> Vec3f {
+        let (x, y, z) = (direction.0, direction.1, direction.2);
+        let (face, s, t, major) = if x.abs() >= y.abs() && x.abs() >= z.abs() {
+            if x > 0.0 { (0, -z, -y, x) } else { (1, z, -y, x) }
+        }
+        else if y.abs() >= z.abs() {
+            if y > 0.0 { (2, x, z, y) } else { (3, x, -z, y) }
+        }
+        else if z > 0.0 {
+            (4, x, -y, z)
+        }
+        else {
+            (5, -x, -y, z)
+        };
+        let major = major.abs();
+        self.faces[face].sample((s / major + 1.0) / 2.0, (t / major + 1.0) / 2.0)
+    }
+}
+
+//Fills the pixels no model covers
+pub enum Background {
+    //Display color from 0 to 1
+    Color(Vec3f),
+    //Display colors from 0 to 1 blended from the bottom of the image to the top
+    Gradient { top: Vec3f, bottom: Vec3f },
+    //Surroundings looked up from the direction of each camera ray
+    CubeMap(CubeMap),
+    //Equirectangular surroundings looked up from the direction of each camera ray
+    Panorama(Panorama),
+}
+
+impl Background {
+    //Fills the pixels of a frame buffer which no model covers, so it is drawn after every model
+    pub fn draw(&self, target: &mut FrameBuffer, camera: &Camera) {
+        match self {
+            Background::Color(color) => target.fill_uncovered(|_, _| to_rgb(color)),
+            Background::Gradient { top, bottom } => {
+                let height = target.height() as f32;
+                target.fill_uncovered(|_, y| {
+                    let t = (y as f32 + 0.5) / height;
+                    to_rgb(&(bottom * (1.0 - t) + top * t))
+                })
+            }
+            Background::CubeMap(cube_map) => draw_sky(target, camera, |direction| cube_map.sample(direction)),
+            Background::Panorama(panorama) => draw_sky(target, camera, |direction| panorama.sample(direction)),
+        }
+    }
+}
+
+//Colors each uncovered pixel of a frame buffer with the linear radiance seen along its camera ray
+pub fn draw_sky<F: Fn(&Vec3f) -> Vec3f>(target: &mut FrameBuffer, camera: &Camera, radiance: F) {
+    let inverse = match camera.view_projection().inverse() {
+        Some(inverse) => inverse,
+        None => return,
+    };
+    let (width, height) = (target.width() as f32, target.height() as f32);
+    target.fill_uncovered(|x, y| {
+        //Unprojects the pixel onto the near and far planes, the ray runs between them
+        let ndc_x = (x as f32 + 0.5) / width * 2.0 - 1.0;
+        let ndc_y = (y as f32 + 0.5) / height * 2.0 - 1.0;
+        let near = inverse.transform_point(&Vec3f(ndc_x, ndc_y, -1.0));
+        let far = inverse.transform_point(&Vec3f(ndc_x, ndc_y, 1.0));
+        let color = radiance(&(far - near).normalize());
+        to_rgb(&Vec3f(linear_to_srgb(color.0), linear_to_srgb(color.1), linear_to_srgb(color.2)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid_face(color: Vec3f) -> CubeFace {
+        CubeFace {
+            width: 1,
+            height: 1,
+            pixels: vec![color],
+        }
+    }
+
+    fn camera() -> Camera {
+        Camera::perspective(Vec3f(0.0, 0.0, 3.0), Vec3f(0.0, 0.0, 0.0), 1.0, 1.0)
+    }
+
+    #[test]
+    fn cube_maps_use_the_face_of_the_largest_component() {
+        let colors: Vec<Vec3f> = (0..6).map(|index| Vec3f(index as f32, 0.0, 0.0)).collect();
+        let cube_map = CubeMap {
+            faces: colors.iter().cloned().map(solid_face).collect(),
+        };
+        let directions = [Vec3f(1.0, 0.2, -0.3), Vec3f(-1.0, 0.5, 0.5), Vec3f(0.1, 2.0, 0.0), Vec3f(0.3, -0.9, 0.2), Vec3f(0.0, 0.0, 1.0), Vec3f(-0.6, 0.6, -0.7)];
+        for (index, direction) in directions.iter().enumerate() {
+            assert_eq!(cube_map.sample(direction), colors[index]);
+        }
+    }
+
+    #[test]
+    fn cube_faces_are_stored_top_to_bottom() {
+        let mut faces: Vec<CubeFace> = (0..6).map(|_| solid_face(Vec3f(0.0, 0.0, 0.0))).collect();
+        faces[4] = CubeFace {
+            width: 2,
+            height: 2,
+            pixels: vec![Vec3f(1.0, 0.0, 0.0), Vec3f(0.0, 1.0, 0.0), Vec3f(0.0, 0.0, 1.0), Vec3f(1.0, 1.0, 1.0)],
+        };
+        let cube_map = CubeMap { faces };
+        //Looking down +z, the first pixel is in the top left corner
+        assert_eq!(cube_map.sample(&Vec3f(-0.5, 0.5, 1.0)), Vec3f(1.0, 0.0, 0.0));
+        assert_eq!(cube_map.sample(&Vec3f(0.5, 0.5, 1.0)), Vec3f(0.0, 1.0, 0.0));
+        assert_eq!(cube_map.sample(&Vec3f(-0.5, -0.5, 1.0)), Vec3f(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn backgrounds_leave_covered_pixels() {
+        let mut target = FrameBuffer::new(2, 2);
+        target.sample_depth[0] = 0.5;
+        target.sample_color[0] = [10, 20, 30];
+        target.sample_coverage[0] = 1.0;
+        target.resolve(0, 0);
+        Background::Color(Vec3f(1.0, 0.0, 1.0)).draw(&mut target, &camera());
+        assert_eq!(target.color.get_pixel(0, 0).0, [10, 20, 30]);
+        for (x, y) in [(1, 0), (0, 1), (1, 1)].iter() {
+            assert_eq!(target.color.get_pixel(*x, *y).0, [255, 0, 255]);
+        }
+    }
+
+    #[test]
+    fn gradients_blend_from_the_bottom_row_to_the_top() {
+        let mut target = FrameBuffer::new(1, 4);
+        Background::Gradient { top: Vec3f(1.0, 1.0, 1.0), bottom: Vec3f(0.0, 0.0, 0.0) }.draw(&mut target, &camera());
+        let values: Vec<u8> = (0..4).map(|y| target.color.get_pixel(0, y).0[0]).collect();
+        assert!(values.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", values);
+    }
+}
\ No newline at end of file
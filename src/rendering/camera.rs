@@ -0,0 +1,129 @@
+use crate::core::matrix::*;
+use crate::core::vector::*;
+use crate::misc::utils::*;
+use crate::rendering::obj::*;
+use std::f32::consts::FRAC_PI_2;
+
+//Largest pitch reachable when orbiting, keeps the eye from crossing the poles
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+//Projects view space onto normalized device coordinates
+#[derive(Debug, PartialEq, Clone)]
+pub enum Projection {
+    //Vertical field of view in radians
+    Perspective { fov: f32 },
+    //Height of the visible volume in world units
+    Orthographic { height: f32 },
+}
+
+//Stores the placement and lens of a camera
+#[derive(Debug, PartialEq, Clone)]
+pub struct Camera {
+    pub eye: Vec3f,
+    pub target: Vec3f,
+    pub up: Vec3f,
+    pub projection: Projection,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Camera {
+    //Creates a perspective camera given a vertical field of view in radians
+    pub fn perspective(eye: Vec3f, target: Vec3f, fov: f32, aspect: f32) -> Camera {
+        Camera {
+            eye,
+            target,
+            up: Vec3f(0.0, 1.0, 0.0),
+            projection: Projection::Perspective { fov },
+            aspect,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+
+    //Creates an orthographic camera given the height of the visible volume
+    pub fn orthographic(eye: Vec3f, target: Vec3f, height: f32, aspect: f32) -> Camera {
+        Camera {
+            eye,
+            target,
+            up: Vec3f(0.0, 1.0, 0.0),
+            projection: Projection::Orthographic { height },
+            aspect,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::look_at(&self.eye, &self.target, &self.up)
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fov } => Mat4::perspective(fov, self.aspect, self.near, self.far),
+            Projection::Orthographic { height } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * self.aspect;
+                Mat4::orthographic(-half_width, half_width, -half_height, half_height, self.near, self.far)
+            }
+        }
+    }
+
+    //Combined view and projection matrix
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+
+    //Rotates the eye around the target, yaw turns around the y axis and pitch tilts towards it
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        let offset = &self.eye - &self.target;
+        let distance = Vec3f::magnitude(&offset);
+        let current_yaw = offset.0.atan2(offset.2);
+        let current_pitch = (offset.1 / distance).asin();
+
+        let new_yaw = current_yaw + yaw;
+        let new_pitch = clamp_float(current_pitch + pitch, -MAX_PITCH, MAX_PITCH);
+        let new_offset = Vec3f(new_pitch.cos() * new_yaw.sin(), new_pitch.sin(), new_pitch.cos() * new_yaw.cos()) * distance;
+        self.eye = &self.target + new_offset;
+    }
+
+    //Scales the distance to the target, or the visible height for orthographic cameras
+    pub fn zoom(&mut self, factor: f32) {
+        match &mut self.projection {
+            Projection::Perspective { .. } => {
+                let offset = &self.eye - &self.target;
+                self.eye = &self.target + offset * factor;
+            }
+            Projection::Orthographic { height } => *height *= factor,
+        }
+    }
+
+    //Moves the camera along its current viewing direction so that the whole model is visible, models with no vertices leave it unchanged
+    pub fn frame(&mut self, model: &Model) {
+        if model.vertices.is_empty() {
+            return;
+        }
+        let (min, max) = model.bounds();
+        let center = (&min + &max) * 0.5;
+        let radius = max_float(Vec3f::magnitude(&(&max - &min)) / 2.0, f32::EPSILON);
+        let direction = (&self.eye - &self.target).normalize();
+
+        let distance = match &mut self.projection {
+            Projection::Perspective { fov } => {
+                //The bounding sphere must fit in the narrower of the two fields of view
+                let horizontal_fov = 2.0 * ((*fov / 2.0).tan() * self.aspect).atan();
+                radius / (min_float(*fov, horizontal_fov) / 2.0).sin()
+            }
+            Projection::Orthographic { height } => {
+                *height = 2.0 * radius * max_float(1.0, 1.0 / self.aspect);
+                2.0 * radius
+            }
+        };
+
+        self.eye = &center + direction * distance;
+        self.target = center;
+        self.near = max_float(distance - radius, radius * 0.01);
+        self.far = distance + radius;
+    }
+}
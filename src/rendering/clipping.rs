@@ -0,0 +1,180 @@
+use crate::core::vector::*;
+use crate::rendering::shader::*;
+
+//The six planes of the view frustum in clip space, each inside when its distance is positive
+const FRUSTUM_PLANES: [Vec4f; 6] = [
+    //Left, x >= -w
+    Vec4f(1.0, 0.0, 0.0, 1.0),
+    //Right, x <= w
+    Vec4f(-1.0, 0.0, 0.0, 1.0),
+    //Bottom, y >= -w
+    Vec4f(0.0, 1.0, 0.0, 1.0),
+    //Top, y <= w
+    Vec4f(0.0, -1.0, 0.0, 1.0),
+    //Near, z >= -w
+    Vec4f(0.0, 0.0, 1.0, 1.0),
+    //Far, z <= w
+    Vec4f(0.0, 0.0, -1.0, 1.0),
+];
+
+//Linearly interpolates the position and varyings of two vertices
+fn lerp_vertex(start: &VertexOutput, end: &VertexOutput, t: f32) -> VertexOutput {
+    VertexOutput {
+        position: &start.position + (&end.position - &start.position) * t,
+        varyings: start.varyings.iter().zip(&end.varyings).map(|(start, end)| start + (end - start) * t).collect(),
+    }
+}
+
+//Clips a polygon against a single plane with the Sutherland-Hodgman algorithm
+fn clip_against_plane(polygon: Vec<VertexOutput>, plane: &Vec4f) -> Vec<VertexOutput> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for index in 0..polygon.len() {
+        let current = &polygon[index];
+        let next = &polygon[(index + 1) % polygon.len()];
+        let current_distance = Vec4f::dot(&current.position, plane);
+        let next_distance = Vec4f::dot(&next.position, plane);
+
+        if current_distance >= 0.0 {
+            clipped.push(current.clone());
+        }
+        //Adds the intersection when the edge crosses the plane
+        if (current_distance >= 0.0) != (next_distance >= 0.0) {
+            let t = current_distance / (current_distance - next_distance);
+            clipped.push(lerp_vertex(current, next, t));
+        }
+    }
+    clipped
+}
+
+//Clips a triangle in clip space against the view frustum, returning the triangles which remain visible
+pub fn clip_triangle(vertices: Vec<VertexOutput>) -> Vec<Vec<VertexOutput>> {
+    let distances: Vec<Vec<f32>> = FRUSTUM_PLANES.iter().map(|plane| vertices.iter().map(|vertex| Vec4f::dot(&vertex.position, plane)).collect()).collect();
+
+    //Triangles completely outside of any plane are rejected
+    if distances.iter().any(|plane| plane.iter().all(|distance| *distance < 0.0)) {
+        return vec![];
+    }
+    //Triangles completely inside of every plane are kept as they are
+    if distances.iter().all(|plane| plane.iter().all(|distance| *distance >= 0.0)) {
+        return vec![vertices];
+    }
+
+    let mut polygon = vertices;
+    for plane in FRUSTUM_PLANES.iter() {
+        polygon = clip_against_plane(polygon, plane);
+        if polygon.len() < 3 {
+            return vec![];
+        }
+    }
+
+    //Splits the convex polygon back into a fan of triangles
+    (1..polygon.len() - 1).map(|index| vec![polygon[0].clone(), polygon[index].clone(), polygon[index + 1].clone()]).collect()
+}
+
+//Clips a line in clip space against the view frustum, returning the visible part of it if there is one
+pub fn clip_line(start: &Vec4f, end: &Vec4f) -> Option<(Vec4f, Vec4f)> {
+    let (mut enter, mut exit) = (0.0f32, 1.0f32);
+    for plane in FRUSTUM_PLANES.iter() {
+        let start_distance = Vec4f::dot(start, plane);
+        let end_distance = Vec4f::dot(end, plane);
+        if start_distance < 0.0 && end_distance < 0.0 {
+            return None;
+        }
+        //Moves the ends of the line in to where it crosses the plane
+        let t = start_distance / (start_distance - end_distance);
+        if start_distance < 0.0 {
+            enter = enter.max(t);
+        }
+        else if end_distance < 0.0 {
+            exit = exit.min(t);
+        }
+    }
+    if enter > exit {
+        return None;
+    }
+    let direction = end - start;
+    Some((start + &direction * enter, start + &direction * exit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Creates a vertex whose only varying is its clip space z, so interpolated varyings can be checked against positions
+    fn vertex(x: f32, y: f32, z: f32, w: f32) -> VertexOutput {
+        VertexOutput {
+            position: Vec4f(x, y, z, w),
+            varyings: vec![z],
+        }
+    }
+
+    fn assert_inside_frustum(triangles: &[Vec<VertexOutput>]) {
+        for triangle in triangles {
+            assert_eq!(triangle.len(), 3);
+            for vertex in triangle {
+                for plane in FRUSTUM_PLANES.iter() {
+                    assert!(Vec4f::dot(&vertex.position, plane) >= -1e-5, "{:?} is outside of {:?}", vertex.position, plane);
+                }
+                assert!((vertex.varyings[0] - vertex.position.2).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn triangles_inside_the_frustum_are_kept() {
+        let triangles = clip_triangle(vec![vertex(-0.5, -0.5, 0.0, 1.0), vertex(0.5, -0.5, 0.0, 1.0), vertex(0.0, 0.5, 0.0, 1.0)]);
+        assert_eq!(triangles.len(), 1);
+        assert_eq!(triangles[0][1].position, Vec4f(0.5, -0.5, 0.0, 1.0));
+    }
+
+    #[test]
+    fn triangles_outside_one_plane_are_rejected() {
+        let triangles = clip_triangle(vec![vertex(-0.5, -0.5, -2.0, 1.0), vertex(0.5, -0.5, -3.0, 1.0), vertex(0.0, 0.5, -2.5, 1.0)]);
+        assert!(triangles.is_empty());
+    }
+
+    #[test]
+    fn one_vertex_behind_the_near_plane_leaves_two_triangles() {
+        let triangles = clip_triangle(vec![vertex(-0.5, -0.5, 0.0, 1.0), vertex(0.5, -0.5, 0.0, 1.0), vertex(0.0, 0.5, -3.0, 1.0)]);
+        assert_eq!(triangles.len(), 2);
+        assert_inside_frustum(&triangles);
+
+        //The new vertices lie on the near plane, where z = -w
+        let on_near_plane = triangles.iter().flatten().filter(|vertex| (vertex.position.2 + vertex.position.3).abs() < 1e-5).count();
+        assert!(on_near_plane >= 2);
+    }
+
+    #[test]
+    fn two_vertices_behind_the_near_plane_leave_one_triangle() {
+        let triangles = clip_triangle(vec![vertex(-0.5, -0.5, -3.0, 1.0), vertex(0.5, -0.5, -3.0, 1.0), vertex(0.0, 0.5, 0.5, 1.0)]);
+        assert_eq!(triangles.len(), 1);
+        assert_inside_frustum(&triangles);
+
+        //The edges are cut where they cross z = -1, a third of the way from the visible vertex
+        let cut = &triangles[0].iter().find(|vertex| vertex.position.0 < 0.0).unwrap().position;
+        assert!((cut.0 - (-0.5 / 3.5 * 1.5)).abs() < 1e-5);
+        assert!((cut.2 + 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn clipping_keeps_the_winding_of_the_triangle() {
+        let triangles = clip_triangle(vec![vertex(-0.5, -0.5, 0.0, 1.0), vertex(0.5, -0.5, 0.0, 1.0), vertex(0.0, 0.5, -3.0, 1.0)]);
+        for triangle in &triangles {
+            let points: Vec<Vec3f> = triangle.iter().map(|vertex| vertex.position.perspective_divide()).collect();
+            //Twice the signed area on the screen stays positive for counter clockwise triangles
+            let area = (points[1].0 - points[0].0) * (points[2].1 - points[0].1) - (points[2].0 - points[0].0) * (points[1].1 - points[0].1);
+            assert!(area > 0.0);
+        }
+    }
+
+    #[test]
+    fn lines_are_cut_at_the_frustum() {
+        let (start, end) = clip_line(&Vec4f(0.0, 0.0, -3.0, 1.0), &Vec4f(0.0, 0.0, 0.0, 1.0)).unwrap();
+        assert!((start.2 + 1.0).abs() < 1e-5);
+        assert_eq!(end, Vec4f(0.0, 0.0, 0.0, 1.0));
+
+        //Lines behind the camera and lines passing outside of a corner are both rejected
+        assert!(clip_line(&Vec4f(0.0, 0.0, -3.0, 1.0), &Vec4f(0.5, 0.0, -2.0, 1.0)).is_none());
+        assert!(clip_line(&Vec4f(-2.0, 0.5, 0.0, 1.0), &Vec4f(0.5, 2.0, 0.0, 1.0)).is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,313 @@
+use crate::core::vector::*;
+use crate::misc::utils::*;
+use crate::rendering::background::*;
+use crate::rendering::camera::*;
+use crate::rendering::framebuffer::*;
+use crate::rendering::texture::*;
+use image::codecs::hdr::HdrDecoder;
+use image::ImageError;
+use std::cmp::{max, min};
+use std::f32::consts::{PI, TAU};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+//Number of prefiltered specular maps, spread evenly from roughness 0 to 1
+const SPECULAR_LEVELS: usize = 6;
+//Largest width of a prefiltered specular map, blurry maps need few pixels
+const SPECULAR_WIDTH: usize = 256;
+//Number of directions importance sampled for each pixel of a prefiltered specular map
+const SPECULAR_SAMPLES: u32 = 256;
+
+//Stores the light arriving from every direction as an equirectangular image, with the top row looking straight up
+pub struct Panorama {
+    pub width: usize,
+    pub height: usize,
+    //Linear RGB radiance
+    pub pixels: Vec<Vec3f>,
+}
+
+impl Panorama {
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3f>) -> Panorama {
+        Panorama {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    //Finds the unit direction the center of a pixel looks towards
+    pub fn direction(&self, x: usize, y: usize) -> Vec3f {
+        let longitude = ((x as f32 + 0.5) / self.width as f32 - 0.5) * TAU;
+        let latitude = (0.5 - (y as f32 + 0.5) / self.height as f32) * PI;
+        Vec3f(latitude.cos() * longitude.sin(), latitude.sin(), -latitude.cos() * longitude.cos())
+    }
+
+    //Finds the solid angle covered by a pixel in a row, which shrinks towards the poles
+    fn solid_angle(&self, y: usize) -> f32 {
+        let latitude = (0.5 - (y as f32 + 0.5) / self.height as f32) * PI;
+        (TAU / self.width as f32) * (PI / self.height as f32) * latitude.cos()
+    }
+
+    //Finds the radiance arriving from a direction, blending the four closest pixels
+    pub fn sample(&self, direction: &Vec3f) -> Vec3f {
+        let direction = direction.normalize();
+        let longitude = direction.0.atan2(-direction.2);
+        let latitude = clamp_float(direction.1, -1.0, 1.0).asin();
+        //Converts to pixels, where the centers of pixels lie on half pixels
+        let x = (longitude / TAU + 0.5) * self.width as f32 - 0.5;
+        let y = (0.5 - latitude / PI) * self.height as f32 - 0.5;
+
+        //Wraps around horizontally and stops at the poles vertically
+        bilinear(x, y, |x, y| self.pixels[x.rem_euclid(self.width as i64) as usize + y.clamp(0, self.height as i64 - 1) as usize * self.width].clone())
+    }
+
+    //Creates a panorama half the size by averaging blocks of 2x2 pixels
+    fn downsample(&self) -> Panorama {
+        let width = max(self.width / 2, 1);
+        let height = max(self.height / 2, 1);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (x0, y0) = (min(2 * x, self.width - 1), min(2 * y, self.height - 1));
+                let (x1, y1) = (min(2 * x + 1, self.width - 1), min(2 * y + 1, self.height - 1));
+                let sum = &self.pixels[x0 + y0 * self.width] + &self.pixels[x1 + y0 * self.width] + &self.pixels[x0 + y1 * self.width] + &self.pixels[x1 + y1 * self.width];
+                pixels.push(sum * 0.25);
+            }
+        }
+        Panorama::new(width, height, pixels)
+    }
+}
+
+//Finds the 9 real spherical harmonic basis functions up to the second band in a direction
+fn spherical_harmonics(direction: &Vec3f) -> [f32; 9] {
+    let (x, y, z) = (direction.0, direction.1, direction.2);
+    [
+        0.282_095,
+        0.488_603 * y,
+        0.488_603 * z,
+        0.488_603 * x,
+        1.092_548 * x * y,
+        1.092_548 * y * z,
+        0.315_392 * (3.0 * z * z - 1.0),
+        1.092_548 * x * z,
+        0.546_274 * (x * x - y * y),
+    ]
+}
+
+//Finds a well spread point of a sequence of points in the unit square
+fn hammersley(index: u32, count: u32) -> (f32, f32) {
+    (index as f32 / count as f32, index.reverse_bits() as f32 / 4_294_967_296.0)
+}
+
+//Lights scenes with an image of their surroundings
+pub struct Environment {
+    pub radiance: Panorama,
+    //Radiance blurred by the GGX distribution, from smooth at index 0 to rough at the end
+    pub specular: Vec<Panorama>,
+    //Spherical harmonic coefficients of the irradiance, already convolved with the cosine lobe
+    pub irradiance: Vec<Vec3f>,
+    //Scales all light from the environment
+    pub intensity: f32,
+}
+
+impl Environment {
+    //Loads an equirectangular panorama, Radiance HDR and OpenEXR files keep their full range while other images are treated as sRGB
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Environment, ImageLoadError> {
+        let path = path.as_ref();
+        let is_hdr = match path.extension() {
+            Some(extension) => extension.eq_ignore_ascii_case("hdr"),
+            None => false,
+        };
+        let load_error = |error| ImageLoadError::new(path, error);
+        //The generic image loader converts Radiance HDR files to 8 bits, so they are decoded directly
+        if is_hdr {
+            let file = File::open(path).map_err(|error| load_error(ImageError::IoError(error)))?;
+            let decoder = HdrDecoder::new(BufReader::new(file)).map_err(load_error)?;
+            let metadata = decoder.metadata();
+            let pixels = decoder.read_image_hdr().map_err(load_error)?.iter().map(|pixel| Vec3f(pixel[0], pixel[1], pixel[2])).collect();
+            Ok(Environment::new(Panorama::new(metadata.width as usize, metadata.height as usize, pixels)))
+        }
+        else {
+            let image = image::open(path).map_err(load_error)?;
+            Ok(Environment::new(Panorama::new(image.width() as usize, image.height() as usize, linear_pixels(&image))))
+        }
+    }
+
+    //Precomputes the irradiance and prefiltered specular maps of a panorama
+    pub fn new(radiance: Panorama) -> Environment {
+        let irradiance = project_irradiance(&radiance);
+        let specular = prefilter_specular(&radiance);
+        Environment {
+            radiance,
+            specular,
+            irradiance,
+            intensity: 1.0,
+        }
+    }
+
+    //Finds the light arriving at a surface with the given unit normal, integrated over its hemisphere
+    pub fn irradiance(&self, normal: &Vec3f) -> Vec3f {
+        let basis = spherical_harmonics(normal);
+        let mut irradiance = Vec3f(0.0, 0.0, 0.0);
+        for (coefficient, value) in self.irradiance.iter().zip(basis.iter()) {
+            irradiance = irradiance + coefficient * *value;
+        }
+        //Ringing of the harmonics can dip below zero opposite bright lights
+        Vec3f(max_float(irradiance.0, 0.0), max_float(irradiance.1, 0.0), max_float(irradiance.2, 0.0)) * self.intensity
+    }
+
+    //Finds the light reflected towards a direction by a surface of a given roughness, blending the two closest prefiltered maps
+    pub fn specular(&self, direction: &Vec3f, roughness: f32) -> Vec3f {
+        let level = clamp_float(roughness, 0.0, 1.0) * (self.specular.len() - 1) as f32;
+        let lower = level.floor() as usize;
+        let upper = min(lower + 1, self.specular.len() - 1);
+        let t = level - level.floor();
+        let color = self.specular[lower].sample(direction) * (1.0 - t) + self.specular[upper].sample(direction) * t;
+        color * self.intensity
+    }
+
+    //Finds the radiance seen looking towards a direction
+    pub fn background(&self, direction: &Vec3f) -> Vec3f {
+        self.radiance.sample(direction) * self.intensity
+    }
+
+    //Fills the pixels of a frame buffer which no model covers with the environment seen by the camera
+    pub fn draw_background(&self, target: &mut FrameBuffer, camera: &Camera) {
+        draw_sky(target, camera, |direction| self.background(direction));
+    }
+}
+
+//Projects the radiance of a panorama onto spherical harmonics and convolves it with the cosine lobe, giving irradiance
+fn project_irradiance(radiance: &Panorama) -> Vec<Vec3f> {
+    let mut coefficients = vec![Vec3f(0.0, 0.0, 0.0); 9];
+    for y in 0..radiance.height {
+        let solid_angle = radiance.solid_angle(y);
+        for x in 0..radiance.width {
+            let basis = spherical_harmonics(&radiance.direction(x, y));
+            let color = &radiance.pixels[x + y * radiance.width] * solid_angle;
+            for (coefficient, value) in coefficients.iter_mut().zip(basis.iter()) {
+                *coefficient = &*coefficient + &color * *value;
+            }
+        }
+    }
+    //Convolution with the cosine lobe scales each band by a constant
+    let bands = [PI, 2.0 * PI / 3.0, 2.0 * PI / 3.0, 2.0 * PI / 3.0, PI / 4.0, PI / 4.0, PI / 4.0, PI / 4.0, PI / 4.0];
+    for (coefficient, band) in coefficients.iter_mut().zip(bands.iter()) {
+        *coefficient = &*coefficient * *band;
+    }
+    coefficients
+}
+
+//Blurs a panorama by the GGX distribution at increasing roughness, assuming the view direction equals the normal
+fn prefilter_specular(radiance: &Panorama) -> Vec<Panorama> {
+    //Rough levels sample blurrier copies of the panorama to hide noise
+    let mut sources = vec![radiance.downsample()];
+    while sources[sources.len() - 1].width > 1 {
+        let next = sources[sources.len() - 1].downsample();
+        sources.push(next);
+    }
+
+    let mut levels = vec![Panorama::new(radiance.width, radiance.height, radiance.pixels.clone())];
+    for level in 1..SPECULAR_LEVELS {
+        let roughness = level as f32 / (SPECULAR_LEVELS - 1) as f32;
+        let alpha = roughness * roughness;
+        let width = max(min(radiance.width >> level, SPECULAR_WIDTH >> (level - 1)), 8);
+        let height = max(width / 2, 4);
+        let mut panorama = Panorama::new(width, height, vec![Vec3f(0.0, 0.0, 0.0); width * height]);
+        for y in 0..height {
+            for x in 0..width {
+                let normal = panorama.direction(x, y);
+                let tangent = (&up_vector(&normal) * &normal).normalize();
+                let bitangent = &normal * &tangent;
+
+                let mut total = Vec3f(0.0, 0.0, 0.0);
+                let mut weight = 0.0;
+                for index in 0..SPECULAR_SAMPLES {
+                    //Importance samples a halfway vector from the GGX distribution
+                    let (u1, u2) = hammersley(index, SPECULAR_SAMPLES);
+                    let phi = TAU * u1;
+                    let cos_theta = ((1.0 - u2) / (1.0 + (alpha * alpha - 1.0) * u2)).sqrt();
+                    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                    let half = &tangent * (sin_theta * phi.cos()) + &bitangent * (sin_theta * phi.sin()) + &normal * cos_theta;
+                    let to_light = &half * (2.0 * Vec3f::dot(&normal, &half)) - &normal;
+                    let normal_dot_light = Vec3f::dot(&normal, &to_light);
+                    if normal_dot_light <= 0.0 {
+                        continue;
+                    }
+
+                    //Picks the source level whose pixels cover about as much of the sphere as the sample, biased one level blurrier to hide noise from small bright lights
+                    let density = alpha * alpha / (PI * (cos_theta * cos_theta * (alpha * alpha - 1.0) + 1.0).powi(2));
+                    let sample_angle = 4.0 / (SPECULAR_SAMPLES as f32 * density);
+                    let pixel_angle = 4.0 * PI / (radiance.width * radiance.height) as f32;
+                    let source = clamp_float(0.5 * (sample_angle / pixel_angle).log2() + 1.0, 0.0, (sources.len() - 1) as f32).round() as usize;
+                    let color = if source == 0 { radiance.sample(&to_light) } else { sources[source - 1].sample(&to_light) };
+                    total = total + color * normal_dot_light;
+                    weight += normal_dot_light;
+                }
+                panorama.pixels[x + y * width] = if weight > 0.0 { total * (1.0 / weight) } else { radiance.sample(&normal) };
+            }
+        }
+        levels.push(panorama);
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //A panorama whose radiance depends only on the direction each pixel looks towards
+    fn panorama(radiance: impl Fn(&Vec3f) -> Vec3f) -> Panorama {
+        let (width, height) = (64, 32);
+        let mut panorama = Panorama::new(width, height, vec![]);
+        panorama.pixels = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| radiance(&panorama.direction(x, y))).collect();
+        panorama
+    }
+
+    fn assert_near(actual: &Vec3f, expected: &Vec3f, tolerance: f32) {
+        assert!(Vec3f::magnitude(&(actual - expected)) < tolerance, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn panorama_samples_at_pixel_centers_return_the_pixel() {
+        let panorama = panorama(|direction| Vec3f(direction.0 + 1.0, direction.1 + 1.0, direction.2 + 1.0));
+        for (x, y) in [(0, 0), (13, 7), (40, 16), (63, 31)].iter() {
+            let direction = panorama.direction(*x, *y);
+            assert!((Vec3f::magnitude(&direction) - 1.0).abs() < 1e-5);
+            assert_near(&panorama.sample(&direction), &panorama.pixels[x + y * panorama.width], 1e-4);
+        }
+    }
+
+    #[test]
+    fn constant_environments_give_constant_light() {
+        let environment = Environment::new(panorama(|_| Vec3f(0.5, 1.0, 2.0)));
+        for normal in [Vec3f(0.0, 1.0, 0.0), Vec3f(0.0, -1.0, 0.0), Vec3f(1.0, 0.0, 0.0), Vec3f(0.6, 0.0, -0.8)].iter() {
+            //Constant radiance integrated against the cosine over a hemisphere gives pi times the radiance
+            assert_near(&environment.irradiance(normal), &(Vec3f(0.5, 1.0, 2.0) * PI), 0.05);
+            for roughness in [0.0, 0.5, 1.0].iter() {
+                assert_near(&environment.specular(normal, *roughness), &Vec3f(0.5, 1.0, 2.0), 0.02);
+            }
+        }
+    }
+
+    #[test]
+    fn irradiance_faces_towards_bright_skies() {
+        let environment = Environment::new(panorama(|direction| if direction.1 > 0.0 { Vec3f(1.0, 1.0, 1.0) } else { Vec3f(0.0, 0.0, 0.0) }));
+        let up = environment.irradiance(&Vec3f(0.0, 1.0, 0.0));
+        let side = environment.irradiance(&Vec3f(1.0, 0.0, 0.0));
+        let down = environment.irradiance(&Vec3f(0.0, -1.0, 0.0));
+        assert!(up.0 > side.0 && side.0 > down.0);
+        //Half of the sphere is bright, so sideways surfaces see half of the full sky
+        assert!((side.0 - PI / 2.0).abs() < 0.05, "{:?}", side);
+    }
+
+    #[test]
+    fn missing_environments_name_the_image() {
+        for path in ["missing.hdr", "missing.png"].iter() {
+            let error = Environment::load(path).err().unwrap();
+            assert_eq!(error.path, Path::new(path));
+            assert!(error.to_string().starts_with(&format!("could not load image {}", path)));
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,161 @@
+use crate::core::vector::*;
+use crate::rendering::options::*;
+use image::{ImageBuffer, Rgb};
+
+//Stores the color, depth and normal of every pixel of a render, so that later passes can read them
+pub struct FrameBuffer {
+    //Resolved color of each pixel, the average of its samples
+    pub color: ImageBuffer::<Rgb<u8>, Vec<u8>>,
+    //Depth of the closest surface at each pixel, where greater depths are closer to the camera and empty pixels are -infinity
+    pub depth: Vec<f32>,
+    //View space normal of the closest surface at each pixel
+    pub normals: Vec<Vec3f>,
+    //Positions of the coverage samples within each pixel from 0 to 1
+    pub sample_positions: Vec<Vec2f>,
+    //Depth of each sample, stored with the samples of a pixel next to each other
+    pub sample_depth: Vec<f32>,
+    pub sample_color: Vec<[u8; 3]>,
+    //Fraction of each sample which holds a final color, from 0 when nothing has been drawn to 1 when a surface or background fills it
+    //Samples on the edges of supersampled surfaces are partly filled and store their color multiplied by this fraction
+    pub sample_coverage: Vec<f32>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32) -> FrameBuffer {
+        FrameBuffer::multisampled(width, height, Msaa::Off)
+    }
+
+    //Creates a frame buffer which tests coverage and depth at several samples in each pixel
+    pub fn multisampled(width: u32, height: u32, msaa: Msaa) -> FrameBuffer {
+        FrameBuffer::with_sample_positions(width, height, msaa.sample_positions())
+    }
+
+    //Creates a frame buffer which tests coverage and depth at the given positions within each pixel
+    pub fn with_sample_positions(width: u32, height: u32, sample_positions: Vec<Vec2f>) -> FrameBuffer {
+        let size = (width * height) as usize;
+        let samples = size * sample_positions.len();
+        FrameBuffer {
+            color: ImageBuffer::<Rgb<u8>, Vec<u8>>::new(width, height),
+            depth: vec![-f32::INFINITY; size],
+            normals: vec![Vec3f(0.0, 0.0, 0.0); size],
+            sample_positions,
+            sample_depth: vec![-f32::INFINITY; samples],
+            sample_color: vec![[0, 0, 0]; samples],
+            sample_coverage: vec![0.0; samples],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.color.width() as usize
+    }
+
+    pub fn height(&self) -> usize {
+        self.color.height() as usize
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.sample_positions.len()
+    }
+
+    //Finds the index of the first sample of a pixel
+    pub fn sample_index(&self, x: usize, y: usize) -> usize {
+        (x + y * self.width()) * self.sample_count()
+    }
+
+    //Determines if a surface has been drawn at a pixel
+    pub fn is_covered(&self, x: usize, y: usize) -> bool {
+        self.depth[x + y * self.width()] > -f32::INFINITY
+    }
+
+    //Averages the samples of a pixel into its color, and keeps the depth of the closest sample
+    pub fn resolve(&mut self, x: usize, y: usize) {
+        let start = self.sample_index(x, y);
+        let count = self.sample_count();
+        let mut sum = [0u32; 3];
+        let mut depth = -f32::INFINITY;
+        for sample in start..(start + count) {
+            for (channel, value) in sum.iter_mut().zip(self.sample_color[sample]) {
+                *channel += value as u32;
+            }
+            if self.sample_depth[sample] > depth {
+                depth = self.sample_depth[sample];
+            }
+        }
+        let width = self.width();
+        self.depth[x + y * width] = depth;
+        //Rounds to the nearest value rather than down
+        self.color.get_pixel_mut(x as u32, y as u32).0 = sum.map(|channel| ((channel + count as u32 / 2) / count as u32) as u8);
+    }
+
+    //Colors the part of every sample which nothing fills given the coordinates of its pixel, so that edges blend into what is behind them
+    pub fn fill_uncovered<F: Fn(usize, usize) -> [u8; 3]>(&mut self, color: F) {
+        let count = self.sample_count();
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let start = self.sample_index(x, y);
+                if self.sample_coverage[start..(start + count)].iter().all(|coverage| *coverage >= 1.0) {
+                    continue;
+                }
+                let value = color(x, y);
+                for sample in start..(start + count) {
+                    let remaining = 1.0 - self.sample_coverage[sample];
+                    if remaining > 0.0 {
+                        for (channel, background) in self.sample_color[sample].iter_mut().zip(value) {
+                            *channel = (*channel as f32 + background as f32 * remaining).round().min(255.0) as u8;
+                        }
+                        self.sample_coverage[sample] = 1.0;
+                    }
+                }
+                self.resolve(x, y);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_averages_samples_and_keeps_the_closest_depth() {
+        let mut target = FrameBuffer::multisampled(1, 1, Msaa::X4);
+        target.sample_color.copy_from_slice(&[[255, 0, 0], [255, 0, 0], [0, 0, 255], [0, 0, 0]]);
+        target.sample_depth.copy_from_slice(&[0.2, 0.7, 0.4, -f32::INFINITY]);
+        target.resolve(0, 0);
+        assert_eq!(target.color.get_pixel(0, 0).0, [128, 0, 64]);
+        assert_eq!(target.depth[0], 0.7);
+    }
+
+    #[test]
+    fn fill_uncovered_only_colors_empty_samples() {
+        let mut target = FrameBuffer::multisampled(2, 1, Msaa::X4);
+        //Half of the first pixel is covered by a red surface
+        for sample in 0..2 {
+            target.sample_color[sample] = [255, 0, 0];
+            target.sample_depth[sample] = 0.5;
+            target.sample_coverage[sample] = 1.0;
+        }
+        target.resolve(0, 0);
+        target.fill_uncovered(|_, _| [0, 0, 255]);
+        assert_eq!(target.color.get_pixel(0, 0).0, [128, 0, 128]);
+        assert_eq!(target.color.get_pixel(1, 0).0, [0, 0, 255]);
+        assert!(target.is_covered(0, 0));
+        assert!(!target.is_covered(1, 0));
+    }
+
+    #[test]
+    fn fill_uncovered_blends_into_partly_covered_samples() {
+        let mut target = FrameBuffer::new(1, 1);
+        //A supersampled edge covering half of the sample, stored premultiplied by its coverage
+        target.sample_color[0] = [128, 0, 0];
+        target.sample_depth[0] = 0.5;
+        target.sample_coverage[0] = 0.5;
+        target.fill_uncovered(|_, _| [0, 0, 255]);
+        assert_eq!(target.color.get_pixel(0, 0).0, [128, 0, 128]);
+        assert_eq!(target.sample_coverage[0], 1.0);
+
+        //Filled samples are left alone by later backgrounds
+        target.fill_uncovered(|_, _| [0, 255, 0]);
+        assert_eq!(target.color.get_pixel(0, 0).0, [128, 0, 128]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,199 @@
+use crate::core::vector::*;
+use crate::misc::utils::*;
+use crate::rendering::environment::*;
+use crate::rendering::obj::*;
+use crate::rendering::shadow::*;
+
+//Controls how the light of a point or spot light fades with distance, the light is divided by constant + linear * d + quadratic * d^2
+#[derive(Debug, PartialEq, Clone)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Attenuation {
+    pub fn new(constant: f32, linear: f32, quadratic: f32) -> Attenuation {
+        Attenuation {
+            constant,
+            linear,
+            quadratic,
+        }
+    }
+
+    //Light which follows the inverse square law
+    pub fn inverse_square() -> Attenuation {
+        Attenuation::new(0.0, 0.0, 1.0)
+    }
+
+    //Finds the fraction of light left after traveling a distance
+    pub fn factor(&self, distance: f32) -> f32 {
+        let divisor = self.constant + self.linear * distance + self.quadratic * distance * distance;
+        if divisor > 0.0 { 1.0 / divisor } else { 1.0 }
+    }
+}
+
+impl Default for Attenuation {
+    //No falloff
+    fn default() -> Attenuation {
+        Attenuation::new(1.0, 0.0, 0.0)
+    }
+}
+
+//Source of light in a scene, colors are intensities which may be greater than 1
+#[derive(Debug, PartialEq, Clone)]
+pub enum Light {
+    //Infinitely far light shining in one direction, such as the sun
+    Directional { direction: Vec3f, color: Vec3f },
+    //Light shining in every direction from a point
+    Point { position: Vec3f, color: Vec3f, attenuation: Attenuation },
+    //Light shining from a point in a cone, which fades between the inner and outer angles in radians from its direction
+    Spot { position: Vec3f, direction: Vec3f, color: Vec3f, inner_angle: f32, outer_angle: f32, attenuation: Attenuation },
+}
+
+impl Light {
+    pub fn directional(direction: Vec3f, color: Vec3f) -> Light {
+        Light::Directional { direction: direction.normalize(), color }
+    }
+
+    pub fn point(position: Vec3f, color: Vec3f, attenuation: Attenuation) -> Light {
+        Light::Point { position, color, attenuation }
+    }
+
+    pub fn spot(position: Vec3f, direction: Vec3f, color: Vec3f, inner_angle: f32, outer_angle: f32, attenuation: Attenuation) -> Light {
+        Light::Spot { position, direction: direction.normalize(), color, inner_angle, outer_angle, attenuation }
+    }
+
+    //Finds the unit direction from a world space point towards the light and the color of the light reaching the point
+    pub fn illuminate(&self, point: &Vec3f) -> (Vec3f, Vec3f) {
+        match self {
+            Light::Directional { direction, color } => (direction.normalize().negate(), color.clone()),
+            Light::Point { position, color, attenuation } => {
+                let offset = position - point;
+                let distance = Vec3f::magnitude(&offset);
+                (offset.normalize(), color * attenuation.factor(distance))
+            }
+            Light::Spot { position, direction, color, inner_angle, outer_angle, attenuation } => {
+                let offset = position - point;
+                let distance = Vec3f::magnitude(&offset);
+                let to_light = offset.normalize();
+
+                //Smoothly fades the light from the inner cone to the outer cone
+                let cosine = Vec3f::dot(&to_light.negate(), &direction.normalize());
+                let (inner, outer) = (inner_angle.cos(), outer_angle.cos());
+                let cone = clamp_float((cosine - outer) / max_float(inner - outer, 1e-6), 0.0, 1.0);
+                let cone = cone * cone * (3.0 - 2.0 * cone);
+                (to_light, color * (attenuation.factor(distance) * cone))
+            }
+        }
+    }
+}
+
+//Lights and other state shared by every model rendered in a scene
+pub struct Scene {
+    pub lights: Vec<Light>,
+    //Color of the light reaching every surface regardless of its normal
+    pub ambient: Vec3f,
+    //Surroundings which light the scene from every direction, only the PbrShader uses them since the other shaders light colors which are not linear
+    pub environment: Option<Environment>,
+    //Shadows cast by lights, which are filled in by render_shadows
+    pub shadow_maps: Vec<ShadowMap>,
+}
+
+impl Scene {
+    //Creates a scene lit by a single white light shining away from the default camera
+    pub fn new() -> Scene {
+        Scene {
+            lights: vec![Light::directional(Vec3f(0.0, 0.0, -1.0), Vec3f(1.0, 1.0, 1.0))],
+            ambient: Vec3f(0.1, 0.1, 0.1),
+            environment: None,
+            shadow_maps: vec![],
+        }
+    }
+
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    //Makes the light at an index cast shadows, which needs render_shadows to be called before rendering
+    pub fn cast_shadows(&mut self, light: usize, resolution: usize) {
+        self.shadow_maps.push(ShadowMap::new(light, resolution));
+    }
+
+    //Renders the shadow map of every shadow casting light, every model given casts shadows onto the others and itself
+    pub fn render_shadows(&mut self, models: &[&Model]) {
+        for shadow_map in &mut self.shadow_maps {
+            shadow_map.render(&self.lights[shadow_map.light], models);
+        }
+    }
+
+    //Finds the fraction of a light reaching a world space point with the given unit normal
+    pub fn visibility(&self, light: usize, position: &Vec3f, normal: &Vec3f) -> f32 {
+        match self.shadow_maps.iter().find(|shadow_map| shadow_map.light == light) {
+            Some(shadow_map) => shadow_map.visibility(position, normal),
+            None => 1.0,
+        }
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Scene {
+        Scene::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: &Vec3f, expected: &Vec3f) {
+        assert!(Vec3f::magnitude(&(actual - expected)) < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn directional_lights_shine_towards_their_direction() {
+        let light = Light::directional(Vec3f(0.0, -2.0, 0.0), Vec3f(0.5, 0.5, 0.5));
+        let (to_light, color) = light.illuminate(&Vec3f(10.0, -4.0, 3.0));
+        assert_near(&to_light, &Vec3f(0.0, 1.0, 0.0));
+        assert_near(&color, &Vec3f(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn point_lights_fade_with_distance() {
+        let light = Light::point(Vec3f(0.0, 0.0, 0.0), Vec3f(8.0, 8.0, 8.0), Attenuation::inverse_square());
+        let (to_light, color) = light.illuminate(&Vec3f(0.0, 0.0, 2.0));
+        assert_near(&to_light, &Vec3f(0.0, 0.0, -1.0));
+        assert_near(&color, &Vec3f(2.0, 2.0, 2.0));
+
+        let light = Light::point(Vec3f(0.0, 0.0, 0.0), Vec3f(1.0, 1.0, 1.0), Attenuation::new(1.0, 0.5, 0.25));
+        assert_near(&light.illuminate(&Vec3f(4.0, 0.0, 0.0)).1, &Vec3f(1.0 / 7.0, 1.0 / 7.0, 1.0 / 7.0));
+    }
+
+    #[test]
+    fn spot_lights_fade_between_their_cones() {
+        let light = Light::spot(Vec3f(0.0, 2.0, 0.0), Vec3f(0.0, -1.0, 0.0), Vec3f(1.0, 1.0, 1.0), 20.0_f32.to_radians(), 40.0_f32.to_radians(), Attenuation::default());
+        //Points at an angle from the axis of the spot light, which is 2 units above them
+        let brightness = |angle: f32| {
+            let (_, color) = light.illuminate(&Vec3f(2.0 * angle.to_radians().tan(), 0.0, 0.0));
+            color.0
+        };
+
+        assert!((brightness(0.0) - 1.0).abs() < 1e-5);
+        assert!((brightness(19.0) - 1.0).abs() < 1e-5);
+        assert_eq!(brightness(41.0), 0.0);
+        assert_eq!(brightness(80.0), 0.0);
+
+        //The fade falls off smoothly and monotonically between the cones
+        let fade: Vec<f32> = (21..40).map(|angle| brightness(angle as f32)).collect();
+        assert!(fade.iter().all(|value| *value > 0.0 && *value < 1.0));
+        assert!(fade.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn spot_lights_are_attenuated() {
+        let light = Light::spot(Vec3f(0.0, 0.0, 0.0), Vec3f(1.0, 0.0, 0.0), Vec3f(9.0, 9.0, 9.0), 0.3, 0.5, Attenuation::inverse_square());
+        let (to_light, color) = light.illuminate(&Vec3f(3.0, 0.0, 0.0));
+        assert_near(&to_light, &Vec3f(-1.0, 0.0, 0.0));
+        assert_near(&color, &Vec3f(1.0, 1.0, 1.0));
+    }
+}
\ No newline at end of file
@@ -37,11 +37,11 @@ pub fn draw_line(x0_input: i32, y0_input: i32, x1_input: i32, y1_input: i32, can
 
     //Determines coordinates and sets the color on the canvas
     for x in x0..(x1 + 1) {
-        if steep {
-            canvas.put_pixel(y as u32, x as u32, Rgb(color.clone()));
-        }
-        else {
-            canvas.put_pixel(x as u32, y as u32, Rgb(color.clone()));
+        let (pixel_x, pixel_y) = if steep { (y, x) } else { (x, y) };
+
+        //Points outside of the canvas are skipped
+        if pixel_x >= 0 && pixel_y >= 0 && (pixel_x as u32) < canvas.width() && (pixel_y as u32) < canvas.height() {
+            canvas.put_pixel(pixel_x as u32, pixel_y as u32, Rgb(*color));
         }
 
         error2 += derror2;
@@ -0,0 +1,206 @@
+use crate::core::vector::*;
+use crate::rendering::obj::*;
+use crate::rendering::texture::*;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+//Stores the surface properties of a material from an MTL file
+pub struct Material {
+    pub name: String,
+    //Ka
+    pub ambient: Vec3f,
+    //Kd
+    pub diffuse: Vec3f,
+    //Ks
+    pub specular: Vec3f,
+    //Ns, where 0 leaves the material without highlights
+    pub shininess: f32,
+    //d, where 1 is fully opaque
+    pub dissolve: f32,
+    //map_Kd
+    pub diffuse_map: Option<Texture>,
+    //map_Ks
+    pub specular_map: Option<Texture>,
+    //map_Bump
+    pub bump_map: Option<Texture>,
+    //map_d
+    pub alpha_map: Option<Texture>,
+}
+
+//Surface properties of a face at a point, after textures are applied
+#[derive(Debug, PartialEq, Clone)]
+pub struct Surface {
+    pub ambient: Vec3f,
+    pub diffuse: Vec3f,
+    pub specular: Vec3f,
+    pub shininess: f32,
+    //Opacity, where 1 is fully opaque
+    pub alpha: f32,
+}
+
+impl Material {
+    pub fn new(name: &str) -> Material {
+        Material {
+            name: name.to_string(),
+            ambient: Vec3f(0.0, 0.0, 0.0),
+            diffuse: Vec3f(1.0, 1.0, 1.0),
+            specular: Vec3f(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            dissolve: 1.0,
+            diffuse_map: None,
+            specular_map: None,
+            bump_map: None,
+            alpha_map: None,
+        }
+    }
+
+    //Loads every material of an MTL file, textures are resolved relative to the file
+    //Textures which cannot be loaded are left out and added to warnings
+    pub fn load<P: AsRef<Path>>(path: P, warnings: &mut Vec<ObjError>) -> Result<Vec<Material>, ObjError> {
+        let path = path.as_ref();
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let file = File::open(path)?;
+        Material::from_reader(BufReader::new(file), directory, warnings)
+    }
+
+    //Parses MTL data from a reader, textures are resolved relative to the given directory
+    //Textures which cannot be loaded are left out and added to warnings
+    pub fn from_reader<R: BufRead>(reader: R, directory: &Path, warnings: &mut Vec<ObjError>) -> Result<Vec<Material>, ObjError> {
+        let mut materials: Vec<Material> = vec![];
+
+        //Reads MTL file line by line
+        for (index, line) in reader.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line?;
+            let split: Vec<&str> = line.split_whitespace().collect();
+            if split.is_empty() {
+                continue;
+            }
+            let (keyword, values) = (split[0], &split[1..]);
+
+            if keyword == "newmtl" {
+                if values.is_empty() {
+                    return Err(ObjError::MissingValues { line: line_number, token: keyword.to_string(), expected: 1, found: 0 });
+                }
+                materials.push(Material::new(&values.join(" ")));
+                continue;
+            }
+
+            //Statements before the first material have nothing to apply to
+            let material = match materials.last_mut() {
+                Some(material) => material,
+                None => continue,
+            };
+            match keyword {
+                "Ka" => if let Some(color) = parse_color(line_number, keyword, values)? { material.ambient = color },
+                "Kd" => if let Some(color) = parse_color(line_number, keyword, values)? { material.diffuse = color },
+                "Ks" => if let Some(color) = parse_color(line_number, keyword, values)? { material.specular = color },
+                "Ns" => material.shininess = parse_floats(line_number, keyword, values, 1)?[0],
+                "d" => material.dissolve = parse_floats(line_number, keyword, values, 1)?[0],
+                //Tr is the inverse of d used by some exporters
+                "Tr" => material.dissolve = 1.0 - parse_floats(line_number, keyword, values, 1)?[0],
+                "map_Kd" => material.diffuse_map = load_map(line_number, keyword, values, directory, warnings)?,
+                "map_Ks" => material.specular_map = load_map(line_number, keyword, values, directory, warnings)?,
+                "map_Bump" | "map_bump" | "bump" => material.bump_map = load_map(line_number, keyword, values, directory, warnings)?,
+                "map_d" => material.alpha_map = load_map(line_number, keyword, values, directory, warnings)?,
+                _ => (),
+            }
+        }
+        Ok(materials)
+    }
+}
+
+//Parses an RGB color in an MTL file, where a single value sets every channel
+//Spectral and CIE XYZ colors are not supported, so they return None and the color keeps its default
+fn parse_color(line: usize, keyword: &str, values: &[&str]) -> Result<Option<Vec3f>, ObjError> {
+    if matches!(values.first(), Some(&"spectral") | Some(&"xyz")) {
+        return Ok(None);
+    }
+    let parsed = parse_floats(line, keyword, values, 1)?;
+    match parsed.len() {
+        1 => Ok(Some(Vec3f(parsed[0], parsed[0], parsed[0]))),
+        2 => Err(ObjError::MissingValues { line, token: keyword.to_string(), expected: 3, found: 2 }),
+        _ => Ok(Some(Vec3f(parsed[0], parsed[1], parsed[2]))),
+    }
+}
+
+//Loads the texture of a map statement, whose file name is the last value after any options
+//A texture which cannot be loaded is added to warnings and returns None, so the material is drawn without it
+fn load_map(line: usize, keyword: &str, values: &[&str], directory: &Path, warnings: &mut Vec<ObjError>) -> Result<Option<Texture>, ObjError> {
+    let file_name = match values.last() {
+        Some(file_name) => file_name,
+        None => return Err(ObjError::MissingValues { line, token: keyword.to_string(), expected: 1, found: 0 }),
+    };
+    let path = directory.join(file_name);
+    match image::open(&path) {
+        Ok(image) => Ok(Some(Texture::new(&image))),
+        Err(error) => {
+            warnings.push(ObjError::Texture { path, error });
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn parse(source: &str) -> Result<Vec<Material>, ObjError> {
+        Material::from_reader(source.as_bytes(), Path::new(""), &mut vec![])
+    }
+
+    #[test]
+    fn materials_are_parsed_in_order() {
+        let materials = parse("# comment\nKd 0 1 0\nnewmtl red\nKa 0.1 0.1 0.1\nKd 1 0 0\nKs 0.5 0.5 0.5\nNs 32\nd 0.5\nnewmtl blue\nKd 0 0 1\nTr 0.25\n").unwrap();
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].name, "red");
+        assert_eq!(materials[0].ambient, Vec3f(0.1, 0.1, 0.1));
+        assert_eq!(materials[0].diffuse, Vec3f(1.0, 0.0, 0.0));
+        assert_eq!(materials[0].specular, Vec3f(0.5, 0.5, 0.5));
+        assert_eq!(materials[0].shininess, 32.0);
+        assert_eq!(materials[0].dissolve, 0.5);
+        assert_eq!(materials[1].name, "blue");
+        assert_eq!(materials[1].diffuse, Vec3f(0.0, 0.0, 1.0));
+        assert_eq!(materials[1].dissolve, 0.75);
+    }
+
+    #[test]
+    fn colors_report_missing_values() {
+        match parse("newmtl a\nKd 1 0\n") {
+            Err(ObjError::MissingValues { line, token, expected, found }) => assert_eq!((line, token.as_str(), expected, found), (2, "Kd", 3, 2)),
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn single_values_set_every_channel() {
+        let materials = parse("newmtl a\nKa 0.25\nKs 0.5\n").unwrap();
+        assert_eq!(materials[0].ambient, Vec3f(0.25, 0.25, 0.25));
+        assert_eq!(materials[0].specular, Vec3f(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn spectral_and_xyz_colors_keep_the_default() {
+        let materials = parse("newmtl a\nKd spectral red.rfl 2.0\nKs xyz 0.5 0.5 0.5\nKa 0.1 0.2 0.3\n").unwrap();
+        assert_eq!(materials[0].diffuse, Vec3f(1.0, 1.0, 1.0));
+        assert_eq!(materials[0].specular, Vec3f(0.0, 0.0, 0.0));
+        assert_eq!(materials[0].ambient, Vec3f(0.1, 0.2, 0.3));
+    }
+
+    #[test]
+    fn missing_maps_are_warnings() {
+        let mut warnings = vec![];
+        let materials = Material::from_reader("newmtl a\nmap_Kd -s 1 1 1 missing.png\nmap_Bump missing_normals.png\nKd 0.5 0.5 0.5\n".as_bytes(), Path::new("textures"), &mut warnings).unwrap();
+        assert!(materials[0].diffuse_map.is_none());
+        assert!(materials[0].bump_map.is_none());
+        //The rest of the material is still loaded
+        assert_eq!(materials[0].diffuse, Vec3f(0.5, 0.5, 0.5));
+        let paths: Vec<PathBuf> = warnings.iter().map(|warning| match warning {
+            ObjError::Texture { path, .. } => path.clone(),
+            other => panic!("unexpected warning {:?}", other),
+        }).collect();
+        assert_eq!(paths, vec![Path::new("textures").join("missing.png"), Path::new("textures").join("missing_normals.png")]);
+    }
+}
\ No newline at end of file
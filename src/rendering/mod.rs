@@ -1,4 +1,21 @@
 pub mod line;
 pub mod triangle;
 
-pub mod obj;
\ No newline at end of file
+pub mod background;
+pub mod camera;
+pub mod clipping;
+pub mod environment;
+pub mod framebuffer;
+pub mod light;
+pub mod material;
+pub mod normals;
+pub mod obj;
+pub mod options;
+pub mod pbr;
+pub mod shader;
+pub mod shadow;
+pub mod ssao;
+pub mod supersample;
+pub mod tangents;
+pub mod texture;
+pub mod triangulate;
\ No newline at end of file
@@ -0,0 +1,132 @@
+use crate::core::vector::*;
+use crate::misc::utils::*;
+use crate::rendering::obj::*;
+use std::collections::HashMap;
+
+//Controls how much each face contributes to the normals of its vertices
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NormalWeighting {
+    //Larger faces contribute more
+    Area,
+    //Faces contribute by the angle of their corner at the vertex, which is independent of how the mesh is tessellated
+    Angle,
+}
+
+//Finds the angle in radians of a face at one of its vertices
+fn corner_angle(model: &Model, face_index: usize, corner: usize) -> f32 {
+    let face = &model.faces[face_index];
+    let v = &model.vertices[face[corner].0];
+    let next = &model.vertices[face[(corner + 1) % 3].0];
+    let prev = &model.vertices[face[(corner + 2) % 3].0];
+    let edge1 = next - v;
+    let edge2 = prev - v;
+    let lengths = Vec3f::magnitude(&edge1) * Vec3f::magnitude(&edge2);
+    if lengths == 0.0 {
+        0.0
+    }
+    else {
+        clamp_float(Vec3f::dot(&edge1, &edge2) / lengths, -1.0, 1.0).acos()
+    }
+}
+
+impl Model {
+    //Replaces the normals of the model with smooth vertex normals, faces meeting at more than the crease angle in radians keep a hard edge
+    pub fn generate_normals(&mut self, weighting: NormalWeighting, crease_angle: f32) {
+        //Area weighted normals of each face, whose length is twice the area of the face
+        let face_normals: Vec<Vec3f> = self.faces.iter().map(|face| {
+            let v0 = &self.vertices[face[0].0];
+            let v1 = &self.vertices[face[1].0];
+            let v2 = &self.vertices[face[2].0];
+            (v1 - v0) * (v2 - v0)
+        }).collect();
+        let unit_normals: Vec<Vec3f> = face_normals.iter().map(|normal| {
+            if Vec3f::magnitude(normal) > 0.0 { normal.normalize() } else { Vec3f(0.0, 0.0, 0.0) }
+        }).collect();
+
+        //Finds the contribution of each corner of each face
+        let weighted_normals: Vec<Vec<Vec3f>> = (0..self.faces.len()).map(|face_index| {
+            (0..3).map(|corner| match weighting {
+                NormalWeighting::Area => face_normals[face_index].clone(),
+                NormalWeighting::Angle => &unit_normals[face_index] * corner_angle(self, face_index, corner),
+            }).collect()
+        }).collect();
+
+        //Lists the corners of faces which touch each vertex
+        let mut incident: Vec<Vec<(usize, usize)>> = vec![vec![]; self.vertices.len()];
+        for (face_index, face) in self.faces.iter().enumerate() {
+            for (corner, face_vertex) in face.iter().enumerate() {
+                incident[face_vertex.0].push((face_index, corner));
+            }
+        }
+
+        let crease_cosine = crease_angle.cos();
+        let mut normals: Vec<Vec3f> = vec![];
+        //Shares normals between corners of a vertex which end up with the same normal
+        let mut normal_indices: HashMap<(usize, [u32; 3]), usize> = HashMap::new();
+
+        for face_index in 0..self.faces.len() {
+            for corner in 0..3 {
+                let vertex = self.faces[face_index][corner].0;
+                let mut normal = Vec3f(0.0, 0.0, 0.0);
+                //Only faces within the crease angle of this face are smoothed together
+                for (other_face, other_corner) in &incident[vertex] {
+                    if Vec3f::dot(&unit_normals[face_index], &unit_normals[*other_face]) >= crease_cosine {
+                        normal = normal + &weighted_normals[*other_face][*other_corner];
+                    }
+                }
+                let normal = if Vec3f::magnitude(&normal) > 0.0 { normal.normalize() } else { unit_normals[face_index].clone() };
+
+                let key = (vertex, [normal.0.to_bits(), normal.1.to_bits(), normal.2.to_bits()]);
+                let index = *normal_indices.entry(key).or_insert_with(|| {
+                    normals.push(normal);
+                    normals.len() - 1
+                });
+                self.faces[face_index][corner].2 = index;
+            }
+        }
+        self.normals = normals;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Two faces sharing the edge from the origin along x, folded 45 degrees apart
+    fn folded_model() -> Model {
+        Model::from_reader("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 -1 1\nf 1 2 3\nf 2 1 4\n".as_bytes()).unwrap()
+    }
+
+    fn normal_at(model: &Model, face_index: usize, corner: usize) -> Vec3f {
+        model.normals[model.faces[face_index][corner].2].clone()
+    }
+
+    fn assert_near(actual: &Vec3f, expected: &Vec3f) {
+        assert!(Vec3f::magnitude(&(actual - expected)) < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn faces_within_the_crease_angle_share_normals() {
+        let mut model = folded_model();
+        model.generate_normals(NormalWeighting::Angle, 60.0_f32.to_radians());
+        //Both corners of each shared vertex point to one normal halfway between the faces
+        assert_eq!(model.faces[0][0].2, model.faces[1][1].2);
+        assert_eq!(model.faces[0][1].2, model.faces[1][0].2);
+        assert_eq!(model.normals.len(), 4);
+        assert_near(&normal_at(&model, 0, 0), &Vec3f(0.0, 1.0, 1.0 + 2.0_f32.sqrt()).normalize());
+
+        //Area weighting favors the larger second face
+        model.generate_normals(NormalWeighting::Area, 60.0_f32.to_radians());
+        assert_near(&normal_at(&model, 0, 0), &Vec3f(0.0, 1.0, 2.0).normalize());
+    }
+
+    #[test]
+    fn faces_beyond_the_crease_angle_split_their_vertices() {
+        let mut model = folded_model();
+        model.generate_normals(NormalWeighting::Angle, 30.0_f32.to_radians());
+        assert_ne!(model.faces[0][0].2, model.faces[1][1].2);
+        assert_eq!(model.normals.len(), 6);
+        assert_near(&normal_at(&model, 0, 0), &Vec3f(0.0, 0.0, 1.0));
+        assert_near(&normal_at(&model, 1, 1), &Vec3f(0.0, 1.0, 1.0).normalize());
+    }
+}
\ No newline at end of file
@@ -1,187 +1,542 @@
+use crate::core::matrix::*;
 use crate::core::vector::*;
+use crate::misc::utils::*;
+use crate::rendering::camera::*;
+use crate::rendering::clipping::*;
+use crate::rendering::framebuffer::*;
+use crate::rendering::light::*;
 use crate::rendering::line::*;
+use crate::rendering::options::*;
+use crate::rendering::shader::*;
+use crate::rendering::supersample::*;
 use crate::rendering::triangle::*;
-use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb};
-use std::io::{BufRead, BufReader};
+use crate::rendering::triangulate::*;
+use crate::rendering::material::*;
+use crate::rendering::texture::*;
+use image::{DynamicImage, ImageBuffer, ImageError, Rgb};
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
 
-pub struct Shader {
-    pub uv: Vec<Vec2f>,
-    pub intensity: f32,
+//Error produced when an OBJ file cannot be loaded
+#[derive(Debug)]
+pub enum ObjError {
+    //The file could not be opened or read
+    Io(io::Error),
+    //A value could not be parsed as a number
+    InvalidNumber { line: usize, token: String },
+    //A face index could not be parsed or refers to an element which does not exist
+    InvalidIndex { line: usize, token: String, reason: String },
+    //A statement has fewer values than it requires
+    MissingValues { line: usize, token: String, expected: usize, found: usize },
+    //A face uses a material which is not defined in any material library
+    UnknownMaterial { line: usize, token: String },
+    //A material library referenced by the file could not be loaded
+    Material { path: PathBuf, error: Box<ObjError> },
+    //A texture referenced by a material could not be loaded
+    Texture { path: PathBuf, error: ImageError },
 }
 
-impl Shader {
-    pub fn new() -> Shader {
-        Shader {
-            uv: vec![],
-            intensity: 0.0,
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(error) => write!(f, "could not read OBJ file: {}", error),
+            ObjError::InvalidNumber { line, token } => write!(f, "line {}: `{}` is not a valid number", line, token),
+            ObjError::InvalidIndex { line, token, reason } => write!(f, "line {}: invalid index `{}`, {}", line, token, reason),
+            ObjError::MissingValues { line, token, expected, found } => write!(f, "line {}: `{}` expects {} values but found {}", line, token, expected, found),
+            ObjError::UnknownMaterial { line, token } => write!(f, "line {}: material `{}` is not defined", line, token),
+            ObjError::Material { path, error } => write!(f, "in material library {}: {}", path.display(), error),
+            ObjError::Texture { path, error } => write!(f, "could not load texture {}: {}", path.display(), error),
         }
     }
+}
 
-    pub fn compute_color(&self, point: &Vec3f, model: &Model) -> [u8; 3] {
-        if self.uv.len() > 0 {
-            let uv = (point.0 * &self.uv[0]) + (point.1 * &self.uv[1]) + (point.2 * &self.uv[2]);
-            let diffuse = model.diffuse(uv);
-            [(self.intensity * diffuse[0] as f32) as u8, (self.intensity * diffuse[1] as f32) as u8, (self.intensity * diffuse[2] as f32) as u8] 
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io(error) => Some(error),
+            ObjError::Material { error, .. } => Some(error.as_ref()),
+            ObjError::Texture { error, .. } => Some(error),
+            _ => None,
         }
-        else {
-            [(self.intensity * 255.0) as u8, (self.intensity * 255.0) as u8, (self.intensity * 255.0) as u8]
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(error: io::Error) -> ObjError {
+        ObjError::Io(error)
+    }
+}
+
+//Parses a number in an OBJ file
+pub(crate) fn parse_float(line: usize, token: &str) -> Result<f32, ObjError> {
+    token.parse::<f32>().map_err(|_| ObjError::InvalidNumber { line, token: token.to_string() })
+}
+
+//Parses the numbers following a statement, requiring at least the given amount
+pub(crate) fn parse_floats(line: usize, keyword: &str, values: &[&str], expected: usize) -> Result<Vec<f32>, ObjError> {
+    if values.len() < expected {
+        return Err(ObjError::MissingValues { line, token: keyword.to_string(), expected, found: values.len() });
+    }
+    values.iter().map(|value| parse_float(line, value)).collect()
+}
+
+//Parses an index in an OBJ file into a zero based index, negative indices count back from the last element defined so far
+fn parse_index(line: usize, token: &str, count: usize) -> Result<usize, ObjError> {
+    let invalid = |reason: String| ObjError::InvalidIndex { line, token: token.to_string(), reason };
+    let index = token.parse::<i64>().map_err(|_| invalid("expected an integer".to_string()))?;
+    if index == 0 {
+        Err(invalid("indices start at 1".to_string()))
+    }
+    else if index > 0 && index as usize <= count {
+        Ok(index as usize - 1)
+    }
+    else if index < 0 && index.unsigned_abs() as usize <= count {
+        Ok(count - index.unsigned_abs() as usize)
+    }
+    else {
+        Err(invalid(format!("only {} elements are defined", count)))
+    }
+}
+
+//Index stored for a texture coordinate or normal which a face vertex leaves out, it is past the end of every list
+pub const MISSING_INDEX: usize = usize::MAX;
+
+//Parses a vertex of a face in the form v, v/vt, v//vn or v/vt/vn, missing indices are set to MISSING_INDEX
+fn parse_face_vertex(line: usize, token: &str, counts: &Vec3u) -> Result<Vec3u, ObjError> {
+    let slash_split: Vec<&str> = token.split('/').collect();
+    if slash_split.len() > 3 {
+        return Err(ObjError::InvalidIndex { line, token: token.to_string(), reason: "expected at most a vertex, texture and normal index".to_string() });
+    }
+
+    let mut face_vertex = Vec3u(0, MISSING_INDEX, MISSING_INDEX);
+    for (slot, index) in slash_split.iter().enumerate() {
+        //Only the vertex index is required
+        if slot > 0 && index.is_empty() {
+            continue;
         }
+        face_vertex.set(slot, parse_index(line, index, counts.get(slot))?);
     }
+    Ok(face_vertex)
 }
 
 //Stores OBJ file data
 pub struct Model {
     pub vertices: Vec<Vec3f>,
+    //Triangles made of vertex, texture and normal indices, where left out texture and normal indices are MISSING_INDEX
     pub faces: Vec<Vec<Vec3u>>,
     pub uv: Vec<Vec2f>,
-    pub diffuse: Option<DynamicImage>,
+    pub normals: Vec<Vec3f>,
+    pub materials: Vec<Material>,
+    //Index into materials of each face, None for faces before the first usemtl statement
+    pub face_materials: Vec<Option<usize>>,
+    pub diffuse: Option<Texture>,
+    //Tangent space normal map, stored as colors from 0 to 1 which map to components from -1 to 1
+    pub normal_map: Option<Texture>,
+    //Tangent and bitangent of each corner of each face, empty until generate_tangents is called
+    pub tangents: Vec<Vec<Vec3f>>,
+    pub bitangents: Vec<Vec<Vec3f>>,
+    //Transforms the model from object space to world space
+    pub transform: Mat4,
+    //Problems which were skipped while loading, such as missing material libraries, textures or materials
+    pub warnings: Vec<ObjError>,
 }
 
 impl Model {
-    pub fn new(file: File) -> Model {
-        let file = BufReader::new(file);
-        let lines: Vec<String> = file.lines().map(|line| line.unwrap()).collect();
+    //Loads an OBJ file from a path, material libraries are resolved relative to the file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Model, ObjError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        Model::parse(BufReader::new(file), path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    //Parses OBJ data from a reader, material libraries are resolved relative to the working directory
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Model, ObjError> {
+        Model::parse(reader, Path::new(""))
+    }
+
+    fn parse<R: BufRead>(reader: R, directory: &Path) -> Result<Model, ObjError> {
         let mut vertices: Vec<Vec3f> = vec![];
         let mut faces: Vec<Vec<Vec3u>> = vec![];
         let mut uv: Vec<Vec2f> = vec![];
+        let mut normals: Vec<Vec3f> = vec![];
+        let mut materials: Vec<Material> = vec![];
+        let mut face_materials: Vec<Option<usize>> = vec![];
+        let mut current_material: Option<usize> = None;
+        let mut warnings: Vec<ObjError> = vec![];
 
         //Reads OBJ file line by line
-        for line in lines {
-            if !line.chars().nth(0).is_none() {
-                let first = line.chars().nth(0).unwrap();
-                match first {
-                    'v' => {
-                        let second = line.chars().nth(1).unwrap();
-                        if second == ' ' {
-                            //Parses a vertice in an OBJ file
-                            let mut split: Vec<&str> = line.split(" ").collect();
-                            split.retain(|string| string != &"");
-                            vertices.push(Vec3f(split[1].parse::<f32>().unwrap(), split[2].parse::<f32>().unwrap(), split[3].parse::<f32>().unwrap()));
-                        }
-                        else if second == 't' {
-                            //Parses a vertice in an OBJ file
-                            let mut split: Vec<&str> = line.split(" ").collect();
-                            split.retain(|string| string != &"");
-                            uv.push(Vec2f(split[1].parse::<f32>().unwrap(), split[2].parse::<f32>().unwrap())); 
-                        }
+        for (index, line) in reader.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line?;
+            let split: Vec<&str> = line.split_whitespace().collect();
+            if split.is_empty() {
+                continue;
+            }
+            let (keyword, values) = (split[0], &split[1..]);
+            match keyword {
+                "v" => {
+                    //Parses a vertice in an OBJ file
+                    let parsed = parse_floats(line_number, keyword, values, 3)?;
+                    vertices.push(Vec3f(parsed[0], parsed[1], parsed[2]));
+                }
+                "vt" => {
+                    //Parses a texture coordinate in an OBJ file, where v defaults to 0
+                    let parsed = parse_floats(line_number, keyword, values, 1)?;
+                    uv.push(Vec2f(parsed[0], parsed.get(1).cloned().unwrap_or(0.0)));
+                }
+                "vn" => {
+                    //Parses a vertex normal in an OBJ file
+                    let parsed = parse_floats(line_number, keyword, values, 3)?;
+                    normals.push(Vec3f(parsed[0], parsed[1], parsed[2]));
+                }
+                "f" => {
+                    //Parses a face in an OBJ file
+                    if values.len() < 3 {
+                        return Err(ObjError::MissingValues { line: line_number, token: keyword.to_string(), expected: 3, found: values.len() });
                     }
-                    'f' => {
-                        //Parses a face in an OBJ file
-                        let mut split: Vec<&str> = line.split(" ").collect::<Vec<&str>>();
-                        split.retain(|string| string != &"");
-
-                        if split[1].contains("/") {
-                            split.retain(|subsplit| 
-                                subsplit
-                                    .split("/")
-                                    .collect::<Vec<&str>>()[0]
-                                    .parse::<i32>().is_ok()
-                            );
-    
-                            let mut parsed_split: Vec<Vec3u> = split.iter().map(|subsplit| {
-                                let slash_split: Vec<usize> = subsplit.split("/").map(|string| string.replace("-", "").parse::<usize>().unwrap() - 1).collect();
-                                Vec3u(slash_split[0], slash_split[1], slash_split[2])
-                            }).collect();
-                            
-                            faces.push(vec![parsed_split.remove(0), parsed_split.remove(0), parsed_split.remove(0)]);
-                        }
-                        else {
-                            split.retain(|subsplit| 
-                                subsplit
-                                    .split("/")
-                                    .collect::<Vec<&str>>()[0]
-                                    .parse::<i32>().is_ok()
-                            );
-
-                            let parsed_split = split.iter().map(|subsplit| subsplit.parse::<usize>().unwrap() - 1).collect::<Vec<usize>>();
+                    let counts = Vec3u(vertices.len(), uv.len(), normals.len());
+                    let polygon = values.iter().map(|value| parse_face_vertex(line_number, value, &counts)).collect::<Result<Vec<Vec3u>, ObjError>>()?;
 
-                            faces.push(vec![Vec3u(parsed_split[0], 0, 0), Vec3u(parsed_split[1], 0, 0), Vec3u(parsed_split[2], 0, 0)]);
+                    //Splits quads and larger polygons into triangles
+                    let positions: Vec<Vec3f> = polygon.iter().map(|vertex| vertices[vertex.0].clone()).collect();
+                    for triangle in triangulate(&positions) {
+                        faces.push(triangle.iter().map(|index| polygon[*index].clone()).collect());
+                        face_materials.push(current_material);
+                    }
+                }
+                "mtllib" => {
+                    //Loads every material library listed on the line, libraries which cannot be loaded are skipped
+                    for value in values {
+                        let path = directory.join(value);
+                        let mut library_warnings = vec![];
+                        match Material::load(&path, &mut library_warnings) {
+                            Ok(loaded) => materials.extend(loaded),
+                            Err(error) => warnings.push(ObjError::Material { path: path.clone(), error: Box::new(error) }),
                         }
+                        warnings.extend(library_warnings.into_iter().map(|error| ObjError::Material { path: path.clone(), error: Box::new(error) }));
+                    }
+                }
+                "usemtl" => {
+                    //Selects the material of the following faces, which are drawn without one if it is not defined
+                    let name = values.join(" ");
+                    current_material = materials.iter().position(|material| material.name == name);
+                    if current_material.is_none() {
+                        warnings.push(ObjError::UnknownMaterial { line: line_number, token: name });
                     }
-                    _ => (),
                 }
+                _ => (),
             }
         }
-        Model {
+        Ok(Model {
             vertices,
             faces,
             uv,
+            normals,
+            materials,
+            face_materials,
             diffuse: None,
-        }
+            normal_map: None,
+            tangents: vec![],
+            bitangents: vec![],
+            transform: Mat4::identity(),
+            warnings,
+        })
     }
 
     pub fn load_texture(&mut self, image: DynamicImage) {
-        self.diffuse = Some(image.flipv());
+        self.diffuse = Some(Texture::new(&image));
+    }
+
+    pub fn load_normal_map(&mut self, image: DynamicImage) {
+        self.normal_map = Some(Texture::new(&image));
     }
 
+    //Finds the texture coordinate of a vertex of a face
     pub fn uv(&self, index: usize, face_index: usize) -> Vec2f {
-        if !self.diffuse.is_none() {
-            let texture = self.faces[index][face_index].1;
-            return Vec2f(self.uv[texture].0 * &(self.diffuse.as_ref().unwrap().width() as f32), self.uv[texture].1 * &(self.diffuse.as_ref().unwrap().height() as f32))
+        match self.uv.get(self.faces[index][face_index].1) {
+            Some(uv) => uv.clone(),
+            None => Vec2f(0.0, 0.0),
+        }
+    }
+
+    //Finds the material of a face
+    pub fn material(&self, index: usize) -> Option<&Material> {
+        self.face_materials[index].map(|material| &self.materials[material])
+    }
+
+    //Finds the diffuse texture of a face, preferring the texture of its material over the texture of the model
+    pub fn diffuse_texture(&self, index: usize) -> Option<&Texture> {
+        match self.material(index) {
+            Some(Material { diffuse_map: Some(map), .. }) => Some(map),
+            _ => self.diffuse.as_ref(),
+        }
+    }
+
+    //Finds the normal map of a face, preferring the bump map of its material over the normal map of the model
+    pub fn normal_texture(&self, index: usize) -> Option<&Texture> {
+        match self.material(index) {
+            Some(Material { bump_map: Some(map), .. }) => Some(map),
+            _ => self.normal_map.as_ref(),
+        }
+    }
+
+    //Finds the tangent and bitangent of a vertex of a face, which are zero when they have not been generated
+    pub fn tangent(&self, index: usize, face_index: usize) -> (Vec3f, Vec3f) {
+        match (self.tangents.get(index), self.bitangents.get(index)) {
+            (Some(tangents), Some(bitangents)) => (tangents[face_index].clone(), bitangents[face_index].clone()),
+            _ => (Vec3f(0.0, 0.0, 0.0), Vec3f(0.0, 0.0, 0.0)),
+        }
+    }
+
+    //Finds the minimum and maximum corners of the world space box enclosing every vertice
+    pub fn bounds(&self) -> (Vec3f, Vec3f) {
+        let mut min = Vec3f(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Vec3f(-f32::INFINITY, -f32::INFINITY, -f32::INFINITY);
+        for vertice in &self.vertices {
+            let vertice = self.transform.transform_point(vertice);
+            for index in 0..3 {
+                min.set(index, min_float(min.get(index), vertice.get(index)));
+                max.set(index, max_float(max.get(index), vertice.get(index)));
+            }
         }
-        else {
-            Vec2f(0.0, 0.0)
+        (min, max)
+    }
+
+    //Finds the normal of a vertex of a face, falling back to the normal of the face when the vertex has no normal
+    pub fn normal(&self, index: usize, face_index: usize) -> Vec3f {
+        match self.normals.get(self.faces[index][face_index].2) {
+            Some(normal) => normal.normalize(),
+            None => face_normal(self, index).normalize().negate(),
         }
+    }
 
+    //Finds the diffuse color and opacity of a face at a texture coordinate
+    pub fn diffuse(&self, index: usize, coordinate: &TexCoord, sampler: &Sampler) -> Vec4f {
+        let color = match self.diffuse_texture(index) {
+            Some(texture) => sampler.sample(texture, coordinate),
+            None => Vec4f(1.0, 1.0, 1.0, 1.0),
+        };
+        match self.material(index) {
+            Some(material) => {
+                let alpha = match &material.alpha_map {
+                    Some(map) => sampler.sample(map, coordinate).0 * material.dissolve,
+                    None => material.dissolve,
+                };
+                let tint = &material.diffuse;
+                Vec4f(color.0 * tint.0, color.1 * tint.1, color.2 * tint.2, alpha)
+            }
+            None => color,
+        }
     }
 
-    pub fn diffuse(&self, uv: Vec2f) -> [u8; 4] {
-        let color = self.diffuse.as_ref().unwrap().get_pixel(uv.0 as u32, uv.1 as u32);
-        [color[0], color[1], color[2], 255]
+    //Finds the surface properties of a face at a texture coordinate, faces without a material reflect ambient light with their diffuse color and have no highlights
+    pub fn surface(&self, index: usize, coordinate: &TexCoord, sampler: &Sampler) -> Surface {
+        let diffuse = self.diffuse(index, coordinate, sampler);
+        match self.material(index) {
+            Some(material) => {
+                let specular = match &material.specular_map {
+                    Some(map) => Vec3f::multiply(&material.specular, &sampler.sample(map, coordinate).xyz()),
+                    None => material.specular.clone(),
+                };
+                Surface {
+                    ambient: material.ambient.clone(),
+                    diffuse: diffuse.xyz(),
+                    specular,
+                    shininess: material.shininess,
+                    alpha: diffuse.3,
+                }
+            }
+            None => Surface {
+                ambient: diffuse.xyz(),
+                diffuse: diffuse.xyz(),
+                specular: Vec3f(0.0, 0.0, 0.0),
+                shininess: 0.0,
+                alpha: diffuse.3,
+            },
+        }
     }
 }
 
-pub fn render_wireframe(model: &Model, image: &mut ImageBuffer::<Rgb<u8>, Vec<u8>>) {
-    for index in 0..model.faces.len() {
-        let face = &model.faces[index];
-        let image_width = image.width() as f32;
-        let image_height = image.height() as f32;
+pub fn render_wireframe(model: &Model, camera: &Camera, image: &mut ImageBuffer::<Rgb<u8>, Vec<u8>>) {
+    let view_projection = camera.view_projection() * &model.transform;
+    let viewport = Mat4::viewport(0.0, 0.0, image.width() as f32, image.height() as f32);
+    for face in &model.faces {
         for face_index in 0..3 {
-            //Finds the position of the current and next vertice
-            let v0 = &model.vertices[face[face_index].0 as usize];
-            let v1 = &model.vertices[face[(face_index + 1) % 3].0 as usize];
-            
-            //Coordinates of the first vertice
-            let x0 = ((v0.0 + 1.0) * image_width / 2.0) as i32;
-            let y0 = ((v0.1 + 1.0) * image_height / 2.0) as i32;
+            //Finds the position of the current and next vertice in clip space
+            let start = &view_projection * Vec4f::point(&model.vertices[face[face_index].0]);
+            let end = &view_projection * Vec4f::point(&model.vertices[face[(face_index + 1) % 3].0]);
 
-            //Coordinates of the second vertice
-            let x1 = ((v1.0 + 1.0) * image_width / 2.0) as i32;
-            let y1 = ((v1.1 + 1.0) * image_height / 2.0) as i32;
+            //Edges are clipped before projecting, since points behind the camera have no position on the screen
+            if let Some((start, end)) = clip_line(&start, &end) {
+                let v0 = viewport.transform_point(&start.perspective_divide());
+                let v1 = viewport.transform_point(&end.perspective_divide());
+                draw_line(v0.0 as i32, v0.1 as i32, v1.0 as i32, v1.1 as i32, image, &[255, 255, 255]);
+            }
+        }
+    }
+}
 
-            draw_line(x0, y0, x1, y1, image, &[255, 255, 255]); 
+//Renders a model into a frame buffer, which keeps its depth so that several models can be drawn together
+pub fn render_model(model: &Model, shader: &dyn Shader, camera: &Camera, scene: &Scene, options: &RenderOptions, target: &mut FrameBuffer) {
+    //Supersampled renders draw into a larger frame buffer which is filtered down afterwards
+    if options.supersampling > 1 {
+        render_supersampled(model, shader, camera, scene, options, target);
+        return;
+    }
+    let uniforms = Uniforms::new(model, camera, scene);
+    let viewport = Mat4::viewport(0.0, 0.0, target.width() as f32, target.height() as f32);
+    let pipeline = Pipeline {
+        model,
+        shader,
+        uniforms: &uniforms,
+        options,
+    };
+    for face_index in 0..model.faces.len() {
+        //Runs the vertex stage on each vertice of the face
+        let vertices: Vec<VertexOutput> = (0..3).map(|vertex_index| shader.vertex(model, &uniforms, face_index, vertex_index)).collect();
+        //Face normals point inwards, so they are flipped before being stored
+        let normal = uniforms.view.transform_direction(&uniforms.world_normal(&face_normal(model, face_index)).negate()).normalize();
+
+        //Clipping may split the face into several triangles or remove it entirely
+        for triangle in clip_triangle(vertices) {
+            //Maps clip space coordinates to the screen
+            let screen_points: Vec<Vec3f> = triangle.iter().map(|vertex| viewport.transform_point(&vertex.position.perspective_divide())).collect();
+            if !is_culled(&screen_points, options) {
+                draw_triangle_model(screen_points, &triangle, face_index, &normal, &pipeline, target);
+            }
         }
     }
 }
 
-pub fn render_model(model: &Model, image: &mut ImageBuffer::<Rgb<u8>, Vec<u8>>) {
-    let mut zbuffer: Vec<f32> = vec![-f32::INFINITY; (image.width()* image.height()) as usize];
-    for index in 0..model.faces.len() {
-        let mut shader = Shader::new();
-        let face = &model.faces[index];
-        let mut screen_points: Vec<Vec3f> = Vec::with_capacity(3);
-        let mut world_points: Vec<Vec3f> = Vec::with_capacity(3);
-        let light_direction = Vec3f(0.0, 0.0, -1.0);
-        for face_index in 0..3 {
-            //Computes the screen and face coordinates for each vertice on the face
-            let v = &model.vertices[face[face_index].0];
-            screen_points.push(Vec3f((v.0 + 1.0) * image.width() as f32 / 2.0, (v.1 + 1.0) * image.height() as f32 / 2.0, v.2));
-            world_points.push(v.clone());
-            shader.uv.push(model.uv(index, face_index))
-        }
-        let normal = (&world_points[2] - &world_points[0]) * (&world_points[1] - &world_points[0]);
-        let intensity = Vec3f::dot(&normal.normalize(), &light_direction) * 1.0;
-        let screen_points_u = vec![Vec3u(screen_points[0].0 as usize, screen_points[0].1 as usize, screen_points[0].2 as usize), Vec3u(screen_points[1].0 as usize, screen_points[1].1 as usize, screen_points[1].2 as usize), Vec3u(screen_points[2].0 as usize, screen_points[2].1 as usize, screen_points[2].2 as usize)];
-        shader.intensity = intensity;
-        if intensity > 0.0 {
-            if !model.diffuse.is_none() {
-                draw_triangle_model(screen_points_u, &shader, model, &mut zbuffer, image)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Model, ObjError> {
+        Model::from_reader(source.as_bytes())
+    }
+
+    #[test]
+    fn invalid_numbers_report_their_line() {
+        match parse("v 0 0 0\n\nv 1 x 0\n") {
+            Err(ObjError::InvalidNumber { line, token }) => {
+                assert_eq!(line, 3);
+                assert_eq!(token, "x");
+            }
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn missing_values_report_their_line_and_counts() {
+        match parse("v 0 0\n") {
+            Err(ObjError::MissingValues { line, token, expected, found }) => assert_eq!((line, token.as_str(), expected, found), (1, "v", 3, 2)),
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+        match parse("v 0 0 0\nv 1 0 0\nf 1 2\n") {
+            Err(ObjError::MissingValues { line, token, expected, found }) => assert_eq!((line, token.as_str(), expected, found), (3, "f", 3, 2)),
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn invalid_indices_report_their_line() {
+        let vertices = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+        for (face, token) in [("f 1 2 4", "4"), ("f 0 1 2", "0"), ("f 1 2 -4", "-4"), ("f 1 2 a", "a"), ("f 1/1/1/1 2 3", "1/1/1/1")] {
+            match parse(&format!("{}{}\n", vertices, face)) {
+                Err(ObjError::InvalidIndex { line, token: found, .. }) => {
+                    assert_eq!(line, 4);
+                    assert_eq!(found, token);
+                }
+                other => panic!("unexpected result for {}: {:?}", face, other.err()),
             }
-            else {
-                let intensity_converted = (intensity * 255.0) as u8;
-                draw_triangle(screen_points_u, &mut zbuffer, image, &[intensity_converted, intensity_converted, intensity_converted]);
+        }
+    }
+
+    #[test]
+    fn errors_display_their_line() {
+        let error = parse("v 0 0 0\nvt 1 y\n").err().unwrap();
+        assert_eq!(error.to_string(), "line 2: `y` is not a valid number");
+    }
+
+    #[test]
+    fn missing_files_are_io_errors() {
+        assert!(matches!(Model::load("does/not/exist.obj"), Err(ObjError::Io(_))));
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_element() {
+        let model = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\nv 1 1 0\nf -4 -1 -2\n").unwrap();
+        assert_eq!(model.faces[0], vec![Vec3u(0, MISSING_INDEX, MISSING_INDEX), Vec3u(1, MISSING_INDEX, MISSING_INDEX), Vec3u(2, MISSING_INDEX, MISSING_INDEX)]);
+        assert_eq!(model.faces[1].iter().map(|vertex| vertex.0).collect::<Vec<usize>>(), vec![0, 3, 2]);
+    }
+
+    #[test]
+    fn negative_texture_and_normal_indices_are_resolved() {
+        let model = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvn 0 0 1\nf -3/-2/-1 -2/-1/-1 -1/-1/-1\n").unwrap();
+        assert_eq!(model.faces[0], vec![Vec3u(0, 0, 0), Vec3u(1, 1, 0), Vec3u(2, 1, 0)]);
+    }
+
+    #[test]
+    fn faces_may_leave_out_normals() {
+        let model = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.25 0.5\nvt 1 0\nvn 1 0 0\nf 1/1 2/2 3/1\n").unwrap();
+        assert_eq!(model.faces[0], vec![Vec3u(0, 0, MISSING_INDEX), Vec3u(1, 1, MISSING_INDEX), Vec3u(2, 0, MISSING_INDEX)]);
+        assert_eq!(model.uv(0, 0), Vec2f(0.25, 0.5));
+        //The face normal is used instead of the only normal in the file
+        assert_eq!(model.normal(0, 0), Vec3f(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn faces_may_leave_out_texture_coordinates() {
+        let model = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.7 0.7\nvn 1 0 0\nvn 0 1 0\nf 1//2 2//1 3//2\n").unwrap();
+        assert_eq!(model.faces[0], vec![Vec3u(0, MISSING_INDEX, 1), Vec3u(1, MISSING_INDEX, 0), Vec3u(2, MISSING_INDEX, 1)]);
+        assert_eq!(model.uv(0, 0), Vec2f(0.0, 0.0));
+        assert_eq!(model.normal(0, 1), Vec3f(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn left_out_indices_do_not_read_the_first_element() {
+        let model = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 1 0 0\nvt 0.7 0.7\nf 1/1/1 2/1/1 3/1/1\nf 1 2 3\n").unwrap();
+        assert_eq!(model.normal(0, 0), Vec3f(1.0, 0.0, 0.0));
+        assert_eq!(model.uv(0, 0), Vec2f(0.7, 0.7));
+        assert_eq!(model.normal(1, 0), Vec3f(0.0, 0.0, 1.0));
+        assert_eq!(model.uv(1, 0), Vec2f(0.0, 0.0));
+    }
+
+    #[test]
+    fn unknown_materials_are_warnings_with_their_line() {
+        let model = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl missing\nf 1 2 3\n").unwrap();
+        assert_eq!(model.face_materials, vec![None]);
+        match model.warnings.as_slice() {
+            [ObjError::UnknownMaterial { line, token }] => assert_eq!((*line, token.as_str()), (4, "missing")),
+            warnings => panic!("unexpected warnings {:?}", warnings),
+        }
+    }
+
+    #[test]
+    fn missing_material_libraries_are_warnings() {
+        let model = parse("mtllib missing.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        assert_eq!(model.faces.len(), 1);
+        match model.warnings.as_slice() {
+            [ObjError::Material { path, error }] => {
+                assert_eq!(path.file_name().unwrap(), "missing.mtl");
+                assert!(matches!(error.as_ref(), ObjError::Io(_)));
             }
+            warnings => panic!("unexpected warnings {:?}", warnings),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn wireframe_edges_crossing_the_near_plane_are_clipped() {
+        let model = parse("v -1000 -1 2.9999\nv 1000 1 2.9999\nv 0 0 -5\nf 1 2 3\n").unwrap();
+        let camera = Camera::perspective(Vec3f(0.0, 0.0, 3.0), Vec3f(0.0, 0.0, 0.0), std::f32::consts::FRAC_PI_3, 1.0);
+        let mut image = ImageBuffer::new(64, 64);
+        render_wireframe(&model, &camera, &mut image);
+
+        //The visible parts of the edges running to the far vertex are still drawn
+        assert!(image.pixels().any(|pixel| *pixel == Rgb([255, 255, 255])));
+    }
+}
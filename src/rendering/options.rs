@@ -0,0 +1,153 @@
+use crate::core::vector::*;
+use std::f32::consts::PI;
+
+//Controls how varyings are interpolated across a triangle
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Interpolation {
+    //Interpolates varyings divided by w along with 1/w, which is correct under perspective projection
+    Perspective,
+    //Interpolates varyings linearly in screen space, which warps them under perspective projection
+    Affine,
+}
+
+//Controls which faces are skipped based on their winding on the screen
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CullMode {
+    //Skips faces facing away from the camera
+    Back,
+    //Skips faces facing towards the camera
+    Front,
+    //Draws every face
+    None,
+}
+
+//Order of the vertices of a face as seen on the screen
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+//Number of coverage samples taken in each pixel, surfaces are still shaded once per pixel
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Msaa {
+    Off,
+    X2,
+    X4,
+    X8,
+}
+
+impl Msaa {
+    //Finds the positions of the samples within a pixel from 0 to 1, rotated so that no two share a row or column
+    pub fn sample_positions(&self) -> Vec<Vec2f> {
+        let offsets: &[(f32, f32)] = match self {
+            Msaa::Off => &[(0.0, 0.0)],
+            Msaa::X2 => &[(4.0, 4.0), (-4.0, -4.0)],
+            Msaa::X4 => &[(-2.0, -6.0), (6.0, -2.0), (-6.0, 2.0), (2.0, 6.0)],
+            Msaa::X8 => &[(1.0, -3.0), (-1.0, 3.0), (5.0, 1.0), (-3.0, -5.0), (-5.0, 5.0), (-7.0, -1.0), (3.0, 7.0), (7.0, -7.0)],
+        };
+        //Offsets are measured in sixteenths of a pixel from its center
+        offsets.iter().map(|(x, y)| Vec2f(0.5 + x / 16.0, 0.5 + y / 16.0)).collect()
+    }
+}
+
+//Filter used to shrink a supersampled render down to its target
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DownsampleFilter {
+    //Averages the block of samples within each pixel
+    Box,
+    //Weights samples linearly by their distance, reaching zero one pixel away
+    Tent,
+    //Windowed sinc filter with three lobes, which keeps the most detail but may ring at hard edges
+    Lanczos,
+}
+
+impl DownsampleFilter {
+    //Finds the distance in pixels past which samples have no weight
+    pub fn radius(&self) -> f32 {
+        match self {
+            DownsampleFilter::Box => 0.5,
+            DownsampleFilter::Tent => 1.0,
+            DownsampleFilter::Lanczos => 3.0,
+        }
+    }
+
+    //Finds the weight of a sample given its distance in pixels from the center of a pixel along one axis
+    pub fn weight(&self, distance: f32) -> f32 {
+        let distance = distance.abs();
+        if distance >= self.radius() {
+            return 0.0;
+        }
+        match self {
+            DownsampleFilter::Box => 1.0,
+            DownsampleFilter::Tent => 1.0 - distance,
+            DownsampleFilter::Lanczos => {
+                if distance < 1e-6 {
+                    return 1.0;
+                }
+                let x = PI * distance;
+                let radius = self.radius();
+                radius * x.sin() * (x / radius).sin() / (x * x)
+            }
+        }
+    }
+}
+
+//Settings which control how models are rendered
+#[derive(Debug, PartialEq, Clone)]
+pub struct RenderOptions {
+    pub interpolation: Interpolation,
+    pub cull_mode: CullMode,
+    //Winding of faces which face towards the camera
+    pub front_face: Winding,
+    //Number of times larger in each direction models are rendered before being filtered down, where 1 renders directly
+    pub supersampling: u32,
+    pub downsample_filter: DownsampleFilter,
+}
+
+impl RenderOptions {
+    pub fn new() -> RenderOptions {
+        RenderOptions {
+            interpolation: Interpolation::Perspective,
+            cull_mode: CullMode::Back,
+            front_face: Winding::CounterClockwise,
+            supersampling: 1,
+            downsample_filter: DownsampleFilter::Box,
+        }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_positions_share_no_row_or_column() {
+        for (msaa, count) in [(Msaa::Off, 1), (Msaa::X2, 2), (Msaa::X4, 4), (Msaa::X8, 8)].iter() {
+            let positions = msaa.sample_positions();
+            assert_eq!(positions.len(), *count);
+            for (index, position) in positions.iter().enumerate() {
+                assert!(position.0 > 0.0 && position.0 < 1.0 && position.1 > 0.0 && position.1 < 1.0, "{:?} is outside of the pixel", position);
+                for other in &positions[index + 1..] {
+                    assert!(position.0 != other.0 && position.1 != other.1, "{:?} and {:?} share a row or column", position, other);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sample_positions_are_centered_in_the_pixel() {
+        for msaa in [Msaa::X2, Msaa::X4, Msaa::X8].iter() {
+            let positions = msaa.sample_positions();
+            let count = positions.len() as f32;
+            let center = positions.iter().fold(Vec2f(0.0, 0.0), |sum, position| Vec2f(sum.0 + position.0, sum.1 + position.1));
+            assert!((center.0 / count - 0.5).abs() < 1e-6 && (center.1 / count - 0.5).abs() < 1e-6, "{:?}", msaa);
+        }
+    }
+}
\ No newline at end of file
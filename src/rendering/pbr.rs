@@ -0,0 +1,266 @@
+use crate::core::vector::*;
+use crate::misc::utils::*;
+use crate::rendering::obj::*;
+use crate::rendering::shader::*;
+use crate::rendering::texture::*;
+use std::f32::consts::PI;
+
+//Reflectance of dielectrics seen head on
+const DIELECTRIC_REFLECTANCE: f32 = 0.04;
+
+//Surface properties of the metallic roughness model used by glTF, each texture is multiplied by its constant factor
+pub struct PbrMaterial {
+    //Linear RGB color and opacity
+    pub base_color: Vec4f,
+    //sRGB color and linear opacity
+    pub base_color_map: Option<Texture>,
+    pub metallic: f32,
+    pub roughness: f32,
+    //Roughness is read from the green channel and metallic from the blue channel
+    pub metallic_roughness_map: Option<Texture>,
+    //Ambient occlusion is read from the red channel
+    pub occlusion_map: Option<Texture>,
+    //Blends between no occlusion at 0 and the full occlusion of the map at 1
+    pub occlusion_strength: f32,
+    //Linear RGB light given off by the surface
+    pub emissive: Vec3f,
+    //sRGB color
+    pub emissive_map: Option<Texture>,
+    //Tangent space normal map, which needs tangents from generate_tangents
+    pub normal_map: Option<Texture>,
+}
+
+//Inputs of the metallic roughness model at a point, after textures are applied
+struct PbrSurface {
+    base_color: Vec3f,
+    alpha: f32,
+    metallic: f32,
+    roughness: f32,
+    occlusion: f32,
+    emissive: Vec3f,
+}
+
+impl PbrMaterial {
+    //Creates a white dielectric material with no textures
+    pub fn new() -> PbrMaterial {
+        PbrMaterial {
+            base_color: Vec4f(1.0, 1.0, 1.0, 1.0),
+            base_color_map: None,
+            metallic: 0.0,
+            roughness: 1.0,
+            metallic_roughness_map: None,
+            occlusion_map: None,
+            occlusion_strength: 1.0,
+            emissive: Vec3f(0.0, 0.0, 0.0),
+            emissive_map: None,
+            normal_map: None,
+        }
+    }
+
+    //Applies the textures of the material at a texture coordinate
+    fn surface(&self, sampler: &Sampler, coordinate: &TexCoord) -> PbrSurface {
+        let (base_color, alpha) = match &self.base_color_map {
+            Some(map) => {
+                let texel = sampler.sample(map, coordinate);
+                let linear = Vec3f(srgb_to_linear(texel.0), srgb_to_linear(texel.1), srgb_to_linear(texel.2));
+                (Vec3f::multiply(&self.base_color.xyz(), &linear), self.base_color.3 * texel.3)
+            }
+            None => (self.base_color.xyz(), self.base_color.3),
+        };
+        let (metallic, roughness) = match &self.metallic_roughness_map {
+            Some(map) => {
+                let texel = sampler.sample(map, coordinate);
+                (self.metallic * texel.2, self.roughness * texel.1)
+            }
+            None => (self.metallic, self.roughness),
+        };
+        let occlusion = match &self.occlusion_map {
+            Some(map) => 1.0 + self.occlusion_strength * (sampler.sample(map, coordinate).0 - 1.0),
+            None => 1.0,
+        };
+        let emissive = match &self.emissive_map {
+            Some(map) => {
+                let texel = sampler.sample(map, coordinate);
+                Vec3f::multiply(&self.emissive, &Vec3f(srgb_to_linear(texel.0), srgb_to_linear(texel.1), srgb_to_linear(texel.2)))
+            }
+            None => self.emissive.clone(),
+        };
+        PbrSurface {
+            base_color,
+            alpha,
+            metallic: clamp_float(metallic, 0.0, 1.0),
+            //Perfectly smooth surfaces would give infinitely small highlights
+            roughness: clamp_float(roughness, 0.03, 1.0),
+            occlusion,
+            emissive,
+        }
+    }
+}
+
+impl Default for PbrMaterial {
+    fn default() -> PbrMaterial {
+        PbrMaterial::new()
+    }
+}
+
+//Finds the fraction of light reflected rather than refracted with Schlick's approximation of the Fresnel equations
+pub fn fresnel_schlick(cosine: f32, reflectance: &Vec3f) -> Vec3f {
+    let factor = (1.0 - clamp_float(cosine, 0.0, 1.0)).powi(5);
+    reflectance + (Vec3f(1.0, 1.0, 1.0) - reflectance) * factor
+}
+
+//Finds the density of microfacets facing the halfway vector with the GGX distribution
+pub fn distribution_ggx(normal_dot_half: f32, roughness: f32) -> f32 {
+    let alpha = roughness * roughness;
+    let alpha_squared = alpha * alpha;
+    let denominator = normal_dot_half * normal_dot_half * (alpha_squared - 1.0) + 1.0;
+    alpha_squared / (PI * denominator * denominator)
+}
+
+//Finds the fraction of microfacets which are neither shadowed nor masked with Smith's method and the Schlick GGX approximation
+pub fn geometry_smith(normal_dot_view: f32, normal_dot_light: f32, roughness: f32) -> f32 {
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let schlick_ggx = |cosine: f32| cosine / (cosine * (1.0 - k) + k);
+    schlick_ggx(normal_dot_view) * schlick_ggx(normal_dot_light)
+}
+
+//Approximates the scale and bias applied to the reflectance of a surface when integrating specular light over the environment, which avoids a lookup table
+pub fn environment_brdf(normal_dot_view: f32, roughness: f32) -> (f32, f32) {
+    let r0 = Vec4f(-1.0, -0.0275, -0.572, 0.022) * roughness + Vec4f(1.0, 0.0425, 1.04, -0.04);
+    let a004 = min_float(r0.0 * r0.0, (-9.28 * normal_dot_view).exp2()) * r0.0 + r0.1;
+    (-1.04 * a004 + r0.2, 1.04 * a004 + r0.3)
+}
+
+//Lights surfaces with the Cook-Torrance microfacet model, light colors are radiance so a light of intensity pi matches the brightness of Lambert shading
+pub struct PbrShader {
+    pub material: PbrMaterial,
+    pub sampler: Sampler,
+}
+
+impl PbrShader {
+    pub fn new(material: PbrMaterial) -> PbrShader {
+        PbrShader {
+            material,
+            sampler: Sampler::default(),
+        }
+    }
+}
+
+impl Shader for PbrShader {
+    fn vertex(&self, model: &Model, uniforms: &Uniforms, face_index: usize, vertex_index: usize) -> VertexOutput {
+        let v = &model.vertices[model.faces[face_index][vertex_index].0];
+        let normal = uniforms.world_normal(&model.normal(face_index, vertex_index));
+        let position = uniforms.world_position(v);
+        let (tangent, bitangent) = model.tangent(face_index, vertex_index);
+        let tangent = uniforms.world_tangent(&tangent);
+        let bitangent = uniforms.world_tangent(&bitangent);
+        let uv = model.uv(face_index, vertex_index);
+        VertexOutput {
+            position: uniforms.clip_position(v),
+            varyings: vec![
+                normal.0, normal.1, normal.2,
+                position.0, position.1, position.2,
+                tangent.0, tangent.1, tangent.2,
+                bitangent.0, bitangent.1, bitangent.2,
+                uv.0, uv.1,
+            ],
+        }
+    }
+
+    fn fragment(&self, _model: &Model, uniforms: &Uniforms, fragment: &Fragment) -> Option<[u8; 3]> {
+        let varyings = &fragment.varyings;
+        let coordinate = fragment.tex_coord(12);
+        let surface = self.material.surface(&self.sampler, &coordinate);
+        //Transparent parts of a material are cut out
+        if surface.alpha < ALPHA_CUTOFF {
+            return None;
+        }
+
+        let normal = Vec3f(varyings[0], varyings[1], varyings[2]).normalize();
+        let normal = match &self.material.normal_map {
+            Some(map) => {
+                let tangent = Vec3f(varyings[6], varyings[7], varyings[8]);
+                let bitangent = Vec3f(varyings[9], varyings[10], varyings[11]);
+                perturb_normal(map, &self.sampler, &coordinate, &normal, &tangent, &bitangent)
+            }
+            None => normal,
+        };
+        let position = Vec3f(varyings[3], varyings[4], varyings[5]);
+        let view_direction = (&uniforms.eye - &position).normalize();
+        //Surfaces seen edge on would divide by zero
+        let normal_dot_view = max_float(Vec3f::dot(&normal, &view_direction), 1e-4);
+
+        //Metals tint their reflections and have no diffuse light
+        let dielectric = Vec3f(DIELECTRIC_REFLECTANCE, DIELECTRIC_REFLECTANCE, DIELECTRIC_REFLECTANCE);
+        let reflectance = dielectric * (1.0 - surface.metallic) + &surface.base_color * surface.metallic;
+        let diffuse_color = &surface.base_color * ((1.0 - surface.metallic) / PI);
+
+        let mut color = Vec3f(0.0, 0.0, 0.0);
+        for light in 0..uniforms.scene.lights.len() {
+            let (to_light, radiance) = incoming_light(uniforms.scene, light, &position, &normal);
+            let normal_dot_light = Vec3f::dot(&normal, &to_light);
+            if normal_dot_light <= 0.0 {
+                continue;
+            }
+            let half = (&to_light + &view_direction).normalize();
+            let fresnel = fresnel_schlick(Vec3f::dot(&half, &view_direction), &reflectance);
+            let specular = &fresnel * (distribution_ggx(max_float(Vec3f::dot(&normal, &half), 0.0), surface.roughness) * geometry_smith(normal_dot_view, normal_dot_light, surface.roughness) / (4.0 * normal_dot_view * normal_dot_light));
+            //Light which is not reflected is refracted and scattered back out as diffuse light
+            let diffuse = Vec3f::multiply(&(Vec3f(1.0, 1.0, 1.0) - fresnel), &diffuse_color);
+            color = color + Vec3f::multiply(&(diffuse + specular), &radiance) * normal_dot_light;
+        }
+
+        //Light from the environment is split between the prefiltered reflection and diffuse irradiance
+        let (scale, bias) = environment_brdf(normal_dot_view, surface.roughness);
+        let specular_weight = &reflectance * scale + Vec3f(bias, bias, bias);
+        let diffuse_weight = Vec3f::multiply(&(Vec3f(1.0, 1.0, 1.0) - &specular_weight), &(&surface.base_color * (1.0 - surface.metallic)));
+        let mut ambient = Vec3f::multiply(&uniforms.scene.ambient, &diffuse_weight);
+        if let Some(environment) = &uniforms.scene.environment {
+            //Lambert surfaces reflect irradiance divided by pi
+            ambient = ambient + Vec3f::multiply(&(environment.irradiance(&normal) * (1.0 / PI)), &diffuse_weight);
+            let reflection = Vec3f::reflect(&view_direction.negate(), &normal);
+            ambient = ambient + Vec3f::multiply(&environment.specular(&reflection, surface.roughness), &specular_weight);
+        }
+        let ambient = ambient * surface.occlusion;
+        let color = color + ambient + surface.emissive;
+        Some(to_rgb(&Vec3f(linear_to_srgb(color.0), linear_to_srgb(color.1), linear_to_srgb(color.2))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn fresnel_goes_from_the_reflectance_to_white() {
+        let reflectance = Vec3f(0.04, 0.5, 0.9);
+        assert_eq!(fresnel_schlick(1.0, &reflectance), reflectance);
+        assert_eq!(fresnel_schlick(0.0, &reflectance), Vec3f(1.0, 1.0, 1.0));
+        assert!(fresnel_schlick(0.5, &reflectance).0 > reflectance.0);
+    }
+
+    #[test]
+    fn ggx_distribution_is_normalized() {
+        //The projected area of the microfacets integrated over the hemisphere is the area of the surface
+        let steps = 100000;
+        for roughness in [0.3, 0.6, 1.0].iter() {
+            let step = FRAC_PI_2 / steps as f32;
+            let integral: f32 = (0..steps).map(|index| {
+                let angle = (index as f32 + 0.5) * step;
+                distribution_ggx(angle.cos(), *roughness) * angle.cos() * angle.sin() * step
+            }).sum::<f32>() * 2.0 * PI;
+            assert!((integral - 1.0).abs() < 1e-2, "roughness {} integrates to {}", roughness, integral);
+        }
+    }
+
+    #[test]
+    fn smith_geometry_only_shadows_grazing_angles() {
+        for roughness in [0.1, 0.5, 1.0].iter() {
+            assert!((geometry_smith(1.0, 1.0, *roughness) - 1.0).abs() < 1e-6);
+            let grazing = geometry_smith(0.05, 1.0, *roughness);
+            assert!(grazing > 0.0 && grazing < 1.0);
+            assert!(geometry_smith(0.05, 0.05, *roughness) < grazing);
+        }
+    }
+}
\ No newline at end of file
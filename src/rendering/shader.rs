@@ -0,0 +1,441 @@
+use crate::core::matrix::*;
+use crate::core::vector::*;
+use crate::misc::utils::*;
+use crate::rendering::camera::*;
+use crate::rendering::light::*;
+use crate::rendering::material::*;
+use crate::rendering::obj::*;
+use crate::rendering::options::*;
+use crate::rendering::texture::*;
+
+//Output of the vertex stage for a single vertex of a face
+#[derive(Debug, PartialEq, Clone)]
+pub struct VertexOutput {
+    //Position in clip space
+    pub position: Vec4f,
+    //Attributes which are interpolated across the face
+    pub varyings: Vec<f32>,
+}
+
+//Input of the fragment stage for a single pixel
+pub struct Fragment {
+    pub face_index: usize,
+    pub x: usize,
+    pub y: usize,
+    pub depth: f32,
+    pub varyings: Vec<f32>,
+    //Change in the varyings one pixel to the right
+    pub ddx: Vec<f32>,
+    //Change in the varyings one pixel up
+    pub ddy: Vec<f32>,
+}
+
+impl Fragment {
+    //Reads a texture coordinate stored in two varyings starting at the given index
+    pub fn tex_coord(&self, index: usize) -> TexCoord {
+        TexCoord::new(
+            Vec2f(self.varyings[index], self.varyings[index + 1]),
+            Vec2f(self.ddx[index], self.ddx[index + 1]),
+            Vec2f(self.ddy[index], self.ddy[index + 1]),
+        )
+    }
+}
+
+//Values which stay constant while a model is rendered
+pub struct Uniforms<'a> {
+    pub model: Mat4,
+    pub view: Mat4,
+    pub projection: Mat4,
+    //Combined model, view and projection matrix
+    pub mvp: Mat4,
+    //Transforms object space normals to world space
+    pub normal_matrix: Mat3,
+    pub eye: Vec3f,
+    pub scene: &'a Scene,
+}
+
+impl<'a> Uniforms<'a> {
+    pub fn new(model: &Model, camera: &Camera, scene: &'a Scene) -> Uniforms<'a> {
+        let view = camera.view_matrix();
+        let projection = camera.projection_matrix();
+        Uniforms {
+            mvp: &projection * &view * &model.transform,
+            normal_matrix: model.transform.normal_matrix(),
+            model: model.transform.clone(),
+            view,
+            projection,
+            eye: camera.eye.clone(),
+            scene,
+        }
+    }
+
+    //Transforms an object space point into clip space
+    pub fn clip_position(&self, point: &Vec3f) -> Vec4f {
+        &self.mvp * Vec4f::point(point)
+    }
+
+    //Transforms an object space point into world space
+    pub fn world_position(&self, point: &Vec3f) -> Vec3f {
+        self.model.transform_point(point)
+    }
+
+    //Transforms an object space normal into world space
+    pub fn world_normal(&self, normal: &Vec3f) -> Vec3f {
+        (&self.normal_matrix * normal).normalize()
+    }
+
+    //Transforms an object space tangent into world space without normalizing it
+    pub fn world_tangent(&self, tangent: &Vec3f) -> Vec3f {
+        self.model.transform_direction(tangent)
+    }
+}
+
+//Programmable stages of the render pipeline
+pub trait Shader {
+    //Transforms a vertex of a face and computes its varyings
+    fn vertex(&self, model: &Model, uniforms: &Uniforms, face_index: usize, vertex_index: usize) -> VertexOutput;
+
+    //Computes the color of a fragment, returning None discards it
+    fn fragment(&self, model: &Model, uniforms: &Uniforms, fragment: &Fragment) -> Option<[u8; 3]>;
+}
+
+//State shared by every triangle drawn while rendering a model
+pub struct Pipeline<'a> {
+    pub model: &'a Model,
+    pub shader: &'a dyn Shader,
+    pub uniforms: &'a Uniforms<'a>,
+    pub options: &'a RenderOptions,
+}
+
+//Interpolates the varyings of a face with barycentric weights
+pub fn interpolate_varyings(vertices: &[VertexOutput], weights: &Vec3f) -> Vec<f32> {
+    let mut varyings = vec![0.0; vertices[0].varyings.len()];
+    for (index, vertex) in vertices.iter().enumerate() {
+        let weight = weights.get(index);
+        for (varying, value) in varyings.iter_mut().zip(&vertex.varyings) {
+            *varying += value * weight;
+        }
+    }
+    varyings
+}
+
+//Corrects screen space barycentric weights for perspective by dividing each weight by the w of its vertex
+pub fn perspective_weights(vertices: &[VertexOutput], weights: &Vec3f) -> Vec3f {
+    let corrected = Vec3f(weights.0 / vertices[0].position.3, weights.1 / vertices[1].position.3, weights.2 / vertices[2].position.3);
+    let total = corrected.0 + corrected.1 + corrected.2;
+    corrected * (1.0 / total)
+}
+
+//Finds the unnormalized normal of a face
+pub fn face_normal(model: &Model, face_index: usize) -> Vec3f {
+    let face = &model.faces[face_index];
+    let v0 = &model.vertices[face[0].0];
+    let v1 = &model.vertices[face[1].0];
+    let v2 = &model.vertices[face[2].0];
+    (v2 - v0) * (v1 - v0)
+}
+
+//Opacity below which fragments are discarded
+pub(crate) const ALPHA_CUTOFF: f32 = 0.5;
+
+//Multiplies the diffuse color of a face at a texture coordinate by the color of the light reaching it
+fn shade(model: &Model, sampler: &Sampler, face_index: usize, coordinate: &TexCoord, light: &Vec3f) -> Option<[u8; 3]> {
+    let diffuse = model.diffuse(face_index, coordinate, sampler);
+    //Transparent parts of a material are cut out
+    if diffuse.3 < ALPHA_CUTOFF {
+        return None;
+    }
+    Some(to_rgb(&Vec3f::multiply(&diffuse.xyz(), light)))
+}
+
+//Finds the unit direction towards a light of a scene and the color of its light reaching a world space point, after shadows
+pub(crate) fn incoming_light(scene: &Scene, light: usize, position: &Vec3f, normal: &Vec3f) -> (Vec3f, Vec3f) {
+    let (to_light, color) = scene.lights[light].illuminate(position);
+    (to_light, color * scene.visibility(light, position, normal))
+}
+
+//Sums the Lambert light of every light reaching a world space point with the given unit normal, surfaces lit from behind receive nothing
+fn diffuse_light(scene: &Scene, normal: &Vec3f, position: &Vec3f) -> Vec3f {
+    let mut total = Vec3f(0.0, 0.0, 0.0);
+    for light in 0..scene.lights.len() {
+        let (to_light, color) = incoming_light(scene, light, position, normal);
+        total = total + color * max_float(Vec3f::dot(normal, &to_light), 0.0);
+    }
+    total
+}
+
+//Lights each face with a single Lambert intensity per light, found at the center of the face
+pub struct FlatShader {
+    pub sampler: Sampler,
+}
+
+impl FlatShader {
+    pub fn new() -> FlatShader {
+        FlatShader {
+            sampler: Sampler::default(),
+        }
+    }
+}
+
+impl Default for FlatShader {
+    fn default() -> FlatShader {
+        FlatShader::new()
+    }
+}
+
+impl Shader for FlatShader {
+    fn vertex(&self, model: &Model, uniforms: &Uniforms, face_index: usize, vertex_index: usize) -> VertexOutput {
+        let v = &model.vertices[model.faces[face_index][vertex_index].0];
+        //Face normals point inwards, so they are flipped to face the lights
+        let normal = uniforms.world_normal(&face_normal(model, face_index)).negate();
+        let face = &model.faces[face_index];
+        let center = (&model.vertices[face[0].0] + &model.vertices[face[1].0] + &model.vertices[face[2].0]) * (1.0 / 3.0);
+        let light = &uniforms.scene.ambient + diffuse_light(uniforms.scene, &normal, &uniforms.world_position(&center));
+        let uv = model.uv(face_index, vertex_index);
+        VertexOutput {
+            position: uniforms.clip_position(v),
+            varyings: vec![light.0, light.1, light.2, uv.0, uv.1],
+        }
+    }
+
+    fn fragment(&self, model: &Model, _uniforms: &Uniforms, fragment: &Fragment) -> Option<[u8; 3]> {
+        let light = Vec3f(fragment.varyings[0], fragment.varyings[1], fragment.varyings[2]);
+        shade(model, &self.sampler, fragment.face_index, &fragment.tex_coord(3), &light)
+    }
+}
+
+//Controls where smooth lighting is computed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ShadingMode {
+    //Lights each vertex and interpolates the light across the face
+    Gouraud,
+    //Interpolates the normal across the face and lights each pixel
+    Phong,
+    //Like Phong, but bends the normal with the tangent space normal map of the face, which needs tangents from generate_tangents
+    NormalMapped,
+}
+
+//Bends an interpolated world space normal by a tangent space normal map sampled at a texture coordinate
+pub(crate) fn perturb_normal(map: &Texture, sampler: &Sampler, coordinate: &TexCoord, normal: &Vec3f, tangent: &Vec3f, bitangent: &Vec3f) -> Vec3f {
+    //Makes the interpolated tangent perpendicular to the normal again, faces without tangents keep their normal
+    let tangent = tangent - normal * Vec3f::dot(normal, tangent);
+    if Vec3f::magnitude(&tangent) < 1e-6 {
+        return normal.clone();
+    }
+    let tangent = tangent.normalize();
+    let perpendicular = normal * &tangent;
+    let bitangent = if Vec3f::dot(&perpendicular, bitangent) < 0.0 { perpendicular.negate() } else { perpendicular };
+
+    //Maps each channel of the normal map from [0, 1] to [-1, 1]
+    let texel = sampler.sample(map, coordinate);
+    let offset = Vec3f(texel.0 * 2.0 - 1.0, texel.1 * 2.0 - 1.0, texel.2 * 2.0 - 1.0);
+    (tangent * offset.0 + bitangent * offset.1 + normal * offset.2).normalize()
+}
+
+//Controls how highlights are computed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SpecularModel {
+    //Leaves surfaces without highlights
+    None,
+    //Compares the reflected light direction with the view direction
+    Phong,
+    //Compares the normal with the halfway vector between the light and view directions, which spreads highlights wider at the same shininess
+    BlinnPhong,
+}
+
+//Lights each face with the interpolated normals of its vertices
+pub struct SmoothShader {
+    pub mode: ShadingMode,
+    pub specular: SpecularModel,
+    pub sampler: Sampler,
+}
+
+impl SmoothShader {
+    pub fn new(mode: ShadingMode) -> SmoothShader {
+        SmoothShader {
+            mode,
+            specular: SpecularModel::BlinnPhong,
+            sampler: Sampler::default(),
+        }
+    }
+
+    //Finds the highlight intensity of a surface with the given normal, lit from the given unit direction and seen from the given unit direction
+    fn specular_intensity(&self, normal: &Vec3f, to_light: &Vec3f, view_direction: &Vec3f, shininess: f32) -> f32 {
+        //Surfaces facing away from the light have no highlights, and neither do surfaces without a shininess since any alignment raised to 0 lights the whole surface
+        if Vec3f::dot(normal, to_light) <= 0.0 || shininess <= 0.0 {
+            return 0.0;
+        }
+        let alignment = match self.specular {
+            SpecularModel::None => return 0.0,
+            SpecularModel::Phong => Vec3f::dot(&Vec3f::reflect(&to_light.negate(), normal), view_direction),
+            SpecularModel::BlinnPhong => Vec3f::dot(normal, &(to_light + view_direction).normalize()),
+        };
+        max_float(alignment, 0.0).powf(shininess)
+    }
+
+    //Sums the ambient, diffuse and specular light reaching a world space point with the given unit normal
+    fn illuminate(&self, uniforms: &Uniforms, normal: &Vec3f, position: &Vec3f, shininess: f32) -> (Vec3f, Vec3f, Vec3f) {
+        let view_direction = (&uniforms.eye - position).normalize();
+        let mut diffuse = Vec3f(0.0, 0.0, 0.0);
+        let mut specular = Vec3f(0.0, 0.0, 0.0);
+        for light in 0..uniforms.scene.lights.len() {
+            let (to_light, color) = incoming_light(uniforms.scene, light, position, normal);
+            diffuse = diffuse + &color * max_float(Vec3f::dot(normal, &to_light), 0.0);
+            specular = specular + &color * self.specular_intensity(normal, &to_light, &view_direction, shininess);
+        }
+        (uniforms.scene.ambient.clone(), diffuse, specular)
+    }
+
+    //Combines the ambient, diffuse and specular light reaching a surface into a color
+    fn light(&self, surface: &Surface, ambient: &Vec3f, diffuse: &Vec3f, specular: &Vec3f) -> Option<[u8; 3]> {
+        //Transparent parts of a material are cut out
+        if surface.alpha < ALPHA_CUTOFF {
+            return None;
+        }
+        let color = Vec3f::multiply(ambient, &surface.ambient) + Vec3f::multiply(&surface.diffuse, diffuse) + Vec3f::multiply(&surface.specular, specular);
+        Some(to_rgb(&color))
+    }
+
+    //Lights a pixel given its interpolated world space normal and position
+    fn light_pixel(&self, model: &Model, uniforms: &Uniforms, fragment: &Fragment, coordinate: &TexCoord, normal: &Vec3f, position: &Vec3f) -> Option<[u8; 3]> {
+        let surface = model.surface(fragment.face_index, coordinate, &self.sampler);
+        let (ambient, diffuse, specular) = self.illuminate(uniforms, normal, position, surface.shininess);
+        self.light(&surface, &ambient, &diffuse, &specular)
+    }
+}
+
+impl Shader for SmoothShader {
+    fn vertex(&self, model: &Model, uniforms: &Uniforms, face_index: usize, vertex_index: usize) -> VertexOutput {
+        let v = &model.vertices[model.faces[face_index][vertex_index].0];
+        let normal = uniforms.world_normal(&model.normal(face_index, vertex_index));
+        let position = uniforms.world_position(v);
+        let uv = model.uv(face_index, vertex_index);
+        let varyings = match self.mode {
+            ShadingMode::Gouraud => {
+                //Highlights use the shininess of the material, while specular maps are still applied to each pixel
+                let shininess = model.material(face_index).map_or(0.0, |material| material.shininess);
+                let (ambient, diffuse, specular) = self.illuminate(uniforms, &normal, &position, shininess);
+                vec![
+                    ambient.0, ambient.1, ambient.2,
+                    diffuse.0, diffuse.1, diffuse.2,
+                    specular.0, specular.1, specular.2,
+                    uv.0, uv.1,
+                ]
+            }
+            ShadingMode::Phong => vec![normal.0, normal.1, normal.2, position.0, position.1, position.2, uv.0, uv.1],
+            ShadingMode::NormalMapped => {
+                let (tangent, bitangent) = model.tangent(face_index, vertex_index);
+                let tangent = uniforms.world_tangent(&tangent);
+                let bitangent = uniforms.world_tangent(&bitangent);
+                vec![
+                    normal.0, normal.1, normal.2,
+                    position.0, position.1, position.2,
+                    tangent.0, tangent.1, tangent.2,
+                    bitangent.0, bitangent.1, bitangent.2,
+                    uv.0, uv.1,
+                ]
+            }
+        };
+        VertexOutput {
+            position: uniforms.clip_position(v),
+            varyings,
+        }
+    }
+
+    fn fragment(&self, model: &Model, uniforms: &Uniforms, fragment: &Fragment) -> Option<[u8; 3]> {
+        let varyings = &fragment.varyings;
+        match self.mode {
+            ShadingMode::Gouraud => {
+                let surface = model.surface(fragment.face_index, &fragment.tex_coord(9), &self.sampler);
+                let ambient = Vec3f(varyings[0], varyings[1], varyings[2]);
+                let diffuse = Vec3f(varyings[3], varyings[4], varyings[5]);
+                let specular = Vec3f(varyings[6], varyings[7], varyings[8]);
+                self.light(&surface, &ambient, &diffuse, &specular)
+            }
+            ShadingMode::Phong => {
+                //Interpolated normals are shorter than 1, so they are normalized again
+                let normal = Vec3f(varyings[0], varyings[1], varyings[2]).normalize();
+                let position = Vec3f(varyings[3], varyings[4], varyings[5]);
+                self.light_pixel(model, uniforms, fragment, &fragment.tex_coord(6), &normal, &position)
+            }
+            ShadingMode::NormalMapped => {
+                let normal = Vec3f(varyings[0], varyings[1], varyings[2]).normalize();
+                let position = Vec3f(varyings[3], varyings[4], varyings[5]);
+                let coordinate = fragment.tex_coord(12);
+                let normal = match model.normal_texture(fragment.face_index) {
+                    Some(map) => {
+                        let tangent = Vec3f(varyings[6], varyings[7], varyings[8]);
+                        let bitangent = Vec3f(varyings[9], varyings[10], varyings[11]);
+                        perturb_normal(map, &self.sampler, &coordinate, &normal, &tangent, &bitangent)
+                    }
+                    None => normal,
+                };
+                self.light_pixel(model, uniforms, fragment, &coordinate, &normal, &position)
+            }
+        }
+    }
+}
+
+//Colors each pixel by the direction of its interpolated normal
+pub struct NormalShader;
+
+impl Shader for NormalShader {
+    fn vertex(&self, model: &Model, uniforms: &Uniforms, face_index: usize, vertex_index: usize) -> VertexOutput {
+        let v = &model.vertices[model.faces[face_index][vertex_index].0];
+        let normal = uniforms.world_normal(&model.normal(face_index, vertex_index));
+        VertexOutput {
+            position: uniforms.clip_position(v),
+            varyings: vec![normal.0, normal.1, normal.2],
+        }
+    }
+
+    fn fragment(&self, _model: &Model, _uniforms: &Uniforms, fragment: &Fragment) -> Option<[u8; 3]> {
+        //Maps each component of the normal from [-1, 1] to [0, 255]
+        let normal = Vec3f(fragment.varyings[0], fragment.varyings[1], fragment.varyings[2]).normalize();
+        let channel = |value: f32| ((value + 1.0) * 127.5) as u8;
+        Some([channel(normal.0), channel(normal.1), channel(normal.2)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Creates a vertex whose only varying is its view depth, which is linear in world space
+    fn vertex_at_depth(w: f32) -> VertexOutput {
+        VertexOutput {
+            position: Vec4f(0.0, 0.0, 0.0, w),
+            varyings: vec![w],
+        }
+    }
+
+    #[test]
+    fn perspective_weights_match_affine_at_equal_depth() {
+        let vertices = vec![vertex_at_depth(2.0), vertex_at_depth(2.0), vertex_at_depth(2.0)];
+        let weights = Vec3f(0.2, 0.3, 0.5);
+        assert_eq!(perspective_weights(&vertices, &weights), weights);
+    }
+
+    #[test]
+    fn perspective_weights_favor_nearer_vertices() {
+        let vertices = vec![vertex_at_depth(1.0), vertex_at_depth(3.0), vertex_at_depth(2.0)];
+        //Halfway along the edge on screen lies a quarter of the way along it in world space
+        let weights = Vec3f(0.5, 0.5, 0.0);
+        let corrected = perspective_weights(&vertices, &weights);
+        assert!(Vec3f::magnitude(&(&corrected - &Vec3f(0.75, 0.25, 0.0))) < 1e-6, "{:?}", corrected);
+
+        //Interpolating the depth must give the depth of the surface seen through the pixel, the affine result overshoots it
+        let depth = 1.0 / (0.5 / 1.0 + 0.5 / 3.0);
+        assert!((interpolate_varyings(&vertices, &corrected)[0] - depth).abs() < 1e-5);
+        assert!((interpolate_varyings(&vertices, &weights)[0] - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn surfaces_without_shininess_have_no_highlights() {
+        let shader = SmoothShader::new(ShadingMode::Gouraud);
+        let up = Vec3f(0.0, 1.0, 0.0);
+        assert_eq!(shader.specular_intensity(&up, &up, &Vec3f(1.0, 0.0, 0.0), 0.0), 0.0);
+        assert_eq!(shader.specular_intensity(&up, &up, &up, 0.0), 0.0);
+        assert!((shader.specular_intensity(&up, &up, &up, 32.0) - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::core::matrix::*;
+use crate::core::vector::*;
+use crate::misc::utils::*;
+use crate::rendering::clipping::*;
+use crate::rendering::light::*;
+use crate::rendering::obj::*;
+use crate::rendering::shader::*;
+use crate::rendering::triangle::*;
+use std::f32::consts::FRAC_PI_2;
+
+//Depth of a scene seen from a light in one direction
+pub struct ShadowView {
+    pub view_projection: Mat4,
+    //World space width of a texel at a clip space w of 1
+    pub texel_size: f32,
+    //Depth of the closest surface at each texel, where greater depths are closer to the light
+    pub depth: Vec<f32>,
+}
+
+//Stores the depth of a scene seen from a light, which is compared against to find the points the light cannot reach
+pub struct ShadowMap {
+    //Index of the light in the scene
+    pub light: usize,
+    //Width and height of each view in texels
+    pub resolution: usize,
+    //Depth a point may lie behind the stored depth while still being lit, which keeps surfaces from shadowing themselves
+    pub bias: f32,
+    //Distance in texels that points are moved along their normal before lookup, which also fights self shadowing, scaled by the size of the filter
+    pub normal_offset: f32,
+    //Radius in texels of the square of depths averaged by percentage closer filtering, 0 gives hard shadows
+    pub pcf_radius: usize,
+    //Directional and spot lights use one view, point lights use one for each face of a cube
+    pub views: Vec<ShadowView>,
+}
+
+impl ShadowMap {
+    pub fn new(light: usize, resolution: usize) -> ShadowMap {
+        ShadowMap {
+            light,
+            resolution,
+            bias: 0.002,
+            normal_offset: 1.0,
+            pcf_radius: 1,
+            views: vec![],
+        }
+    }
+
+    //Renders the depth of every model from the light, fitting the views around the bounds of the models
+    pub fn render(&mut self, light: &Light, models: &[&Model]) {
+        let (center, radius) = bounding_sphere(models);
+        let resolution = self.resolution as f32;
+        self.views = light_views(light, &center, radius).into_iter().map(|(view_projection, extent)| ShadowView {
+            view_projection,
+            texel_size: extent / resolution,
+            depth: vec![-f32::INFINITY; self.resolution * self.resolution],
+        }).collect();
+
+        let size = self.resolution as f32;
+        let viewport = Mat4::viewport(0.0, 0.0, size, size);
+        for view in &mut self.views {
+            for model in models {
+                let transform = &view.view_projection * &model.transform;
+                for face in &model.faces {
+                    //Only positions pass through the depth pass, so the vertices have no varyings
+                    let vertices: Vec<VertexOutput> = face.iter().map(|face_vertex| VertexOutput {
+                        position: &transform * Vec4f::point(&model.vertices[face_vertex.0]),
+                        varyings: vec![],
+                    }).collect();
+                    for triangle in clip_triangle(vertices) {
+                        let screen_points: Vec<Vec3f> = triangle.iter().map(|vertex| viewport.transform_point(&vertex.position.perspective_divide())).collect();
+                        draw_triangle_depth(screen_points, &mut view.depth, self.resolution, self.resolution);
+                    }
+                }
+            }
+        }
+    }
+
+    //Finds the fraction of the light reaching a world space point with the given unit normal, from 0 in shadow to 1 fully lit
+    pub fn visibility(&self, position: &Vec3f, normal: &Vec3f) -> f32 {
+        for view in &self.views {
+            let clip = &view.view_projection * Vec4f::point(position);
+            if clip.3 <= 0.0 {
+                continue;
+            }
+            //Texels cover more of the scene further from perspective lights, so the offset grows with distance
+            let offset = self.normal_offset * view.texel_size * clip.3 * (self.pcf_radius + 1) as f32;
+            let clip = &view.view_projection * Vec4f::point(&(position + normal * offset));
+            let ndc = clip.perspective_divide();
+            //Point lights look the point up in whichever face of the cube contains it
+            if ndc.0.abs() > 1.0 || ndc.1.abs() > 1.0 || ndc.2.abs() > 1.0 {
+                continue;
+            }
+            let size = self.resolution as f32;
+            let x = ((ndc.0 + 1.0) / 2.0 * size) as i64;
+            let y = ((ndc.1 + 1.0) / 2.0 * size) as i64;
+            let depth = (1.0 - ndc.2) / 2.0 + self.bias;
+
+            //Percentage closer filtering averages the depth test of nearby texels instead of the depths themselves
+            let radius = self.pcf_radius as i64;
+            let mut lit = 0;
+            let mut total = 0;
+            for offset_y in -radius..radius + 1 {
+                for offset_x in -radius..radius + 1 {
+                    let texel_x = (x + offset_x).clamp(0, self.resolution as i64 - 1) as usize;
+                    let texel_y = (y + offset_y).clamp(0, self.resolution as i64 - 1) as usize;
+                    if depth >= view.depth[texel_x + texel_y * self.resolution] {
+                        lit += 1;
+                    }
+                    total += 1;
+                }
+            }
+            return lit as f32 / total as f32;
+        }
+        //Points outside of every view are never shadowed
+        1.0
+    }
+}
+
+//Finds a sphere enclosing the world space bounds of every model
+fn bounding_sphere(models: &[&Model]) -> (Vec3f, f32) {
+    let mut min = Vec3f(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    let mut max = Vec3f(-f32::INFINITY, -f32::INFINITY, -f32::INFINITY);
+    for model in models {
+        let (model_min, model_max) = model.bounds();
+        for index in 0..3 {
+            min.set(index, min_float(min.get(index), model_min.get(index)));
+            max.set(index, max_float(max.get(index), model_max.get(index)));
+        }
+    }
+    if models.is_empty() {
+        return (Vec3f(0.0, 0.0, 0.0), 1.0);
+    }
+    let center = (&min + &max) * 0.5;
+    (center, max_float(Vec3f::magnitude(&(&max - &min)) / 2.0, f32::EPSILON))
+}
+
+//Finds the view projection matrices a light renders its shadow map with, covering a sphere around the scene, along with the world space width of each view at a clip space w of 1
+fn light_views(light: &Light, center: &Vec3f, radius: f32) -> Vec<(Mat4, f32)> {
+    match light {
+        Light::Directional { direction, .. } => {
+            //Orthographic projection covering the whole sphere, seen from outside of it
+            let direction = direction.normalize();
+            let eye = center - &direction * (2.0 * radius);
+            let view = Mat4::look_at(&eye, center, &up_vector(&direction));
+            let projection = Mat4::orthographic(-radius, radius, -radius, radius, radius, 3.0 * radius);
+            vec![(projection * view, 2.0 * radius)]
+        }
+        Light::Point { position, .. } => {
+            let (near, far) = depth_range(position, center, radius);
+            let projection = Mat4::perspective(FRAC_PI_2, 1.0, near, far);
+            let directions = [
+                Vec3f(1.0, 0.0, 0.0), Vec3f(-1.0, 0.0, 0.0),
+                Vec3f(0.0, 1.0, 0.0), Vec3f(0.0, -1.0, 0.0),
+                Vec3f(0.0, 0.0, 1.0), Vec3f(0.0, 0.0, -1.0),
+            ];
+            directions.iter().map(|direction| (&projection * Mat4::look_at(position, &(position + direction), &up_vector(direction)), 2.0)).collect()
+        }
+        Light::Spot { position, direction, outer_angle, .. } => {
+            let (near, far) = depth_range(position, center, radius);
+            let fov = min_float(2.0 * outer_angle, 3.0);
+            let projection = Mat4::perspective(fov, 1.0, near, far);
+            let direction = direction.normalize();
+            vec![(projection * Mat4::look_at(position, &(position + &direction), &up_vector(&direction)), 2.0 * (fov / 2.0).tan())]
+        }
+    }
+}
+
+//Finds near and far planes for a light at a position which cover a sphere
+fn depth_range(position: &Vec3f, center: &Vec3f, radius: f32) -> (f32, f32) {
+    let far = Vec3f::magnitude(&(center - position)) + radius;
+    let near = max_float(far - 2.0 * radius, far * 0.001);
+    (near, far)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //A square of the given half width facing up at a height
+    fn square(half_width: f32, height: f32) -> Model {
+        let source = format!("v {0} {1} {0}\nv -{0} {1} {0}\nv -{0} {1} -{0}\nv {0} {1} -{0}\nf 1 2 3 4\n", half_width, height);
+        Model::from_reader(source.as_bytes()).unwrap()
+    }
+
+    fn shadow_map(light: &Light, pcf_radius: usize) -> ShadowMap {
+        let ground = square(2.0, 0.0);
+        let occluder = square(0.5, 1.0);
+        let mut shadow_map = ShadowMap::new(0, 128);
+        shadow_map.pcf_radius = pcf_radius;
+        shadow_map.render(light, &[&ground, &occluder]);
+        shadow_map
+    }
+
+    #[test]
+    fn occluders_shadow_the_ground_below_them() {
+        let up = Vec3f(0.0, 1.0, 0.0);
+        for light in [Light::directional(Vec3f(0.0, -1.0, 0.0), Vec3f(1.0, 1.0, 1.0)), Light::point(Vec3f(0.0, 4.0, 0.0), Vec3f(1.0, 1.0, 1.0), Attenuation::default())].iter() {
+            let shadow_map = shadow_map(light, 1);
+            assert_eq!(shadow_map.visibility(&Vec3f(0.0, 0.0, 0.0), &up), 0.0);
+            assert_eq!(shadow_map.visibility(&Vec3f(1.5, 0.0, 1.5), &up), 1.0);
+            //Lit surfaces do not shadow themselves
+            assert_eq!(shadow_map.visibility(&Vec3f(0.2, 1.0, -0.2), &up), 1.0);
+        }
+    }
+
+    #[test]
+    fn percentage_closer_filtering_softens_shadow_edges() {
+        let light = Light::directional(Vec3f(0.0, -1.0, 0.0), Vec3f(1.0, 1.0, 1.0));
+        let up = Vec3f(0.0, 1.0, 0.0);
+        let edge = Vec3f(0.5, 0.0, 0.0);
+        let hard = shadow_map(&light, 0).visibility(&edge, &up);
+        let soft = shadow_map(&light, 2).visibility(&edge, &up);
+        assert!(hard == 0.0 || hard == 1.0);
+        assert!(soft > 0.0 && soft < 1.0, "{}", soft);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,210 @@
+use crate::core::matrix::*;
+use crate::core::vector::*;
+use crate::misc::utils::*;
+use crate::rendering::camera::*;
+use crate::rendering::framebuffer::*;
+
+//Width and height of the tile of random rotations repeated across the screen, which the blur is sized to hide
+const NOISE_SIZE: usize = 4;
+
+//Hashes an integer into a pseudo random number from 0 to 1, so that results are the same on every render
+fn random(seed: u32) -> f32 {
+    let mut hash = seed.wrapping_mul(0x9E37_79B9) ^ 0x85EB_CA6B;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7FEB_352D);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846C_A68B);
+    hash ^= hash >> 16;
+    (hash >> 8) as f32 / (1u32 << 24) as f32
+}
+
+//Darkens creases and corners by finding how much of the hemisphere above each pixel is covered by nearby geometry in the depth buffer
+pub struct Ssao {
+    //Radius of the sampled hemisphere in world units
+    pub radius: f32,
+    //Number of points sampled in the hemisphere of each pixel
+    pub samples: usize,
+    //Distance a sample must lie behind the depth buffer to count as occluded, which keeps flat surfaces from occluding themselves
+    pub bias: f32,
+    //Exponent applied to the result, larger values give darker occlusion
+    pub power: f32,
+    //Radius in pixels of the box blur which hides the noise of the random rotations
+    pub blur_radius: usize,
+}
+
+impl Ssao {
+    pub fn new() -> Ssao {
+        Ssao {
+            radius: 0.5,
+            samples: 16,
+            bias: 0.025,
+            power: 1.0,
+            blur_radius: NOISE_SIZE / 2,
+        }
+    }
+
+    //Finds the fraction of ambient light reaching each pixel of a frame buffer rendered by a camera, from 0 fully occluded to 1 open
+    pub fn occlusion(&self, target: &FrameBuffer, camera: &Camera) -> Vec<f32> {
+        let (width, height) = (target.width(), target.height());
+        let projection = camera.projection_matrix();
+        let inverse_projection = match projection.inverse() {
+            Some(inverse) => inverse,
+            None => return vec![1.0; width * height],
+        };
+        let kernel = self.kernel();
+
+        let mut occlusion = vec![1.0; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                if !target.is_covered(x, y) {
+                    continue;
+                }
+                let position = view_position(target, &inverse_projection, x, y);
+                let normal = &target.normals[x + y * width];
+
+                //Rotates the kernel around the normal by an angle which repeats every few pixels
+                let angle = random(((x % NOISE_SIZE) + (y % NOISE_SIZE) * NOISE_SIZE) as u32) * std::f32::consts::TAU;
+                let random_direction = Vec3f(angle.cos(), angle.sin(), 0.0);
+                let tangent = &random_direction - normal * Vec3f::dot(normal, &random_direction);
+                let tangent = if Vec3f::magnitude(&tangent) > 1e-6 { tangent.normalize() } else { Vec3f(0.0, 0.0, 1.0) };
+                let bitangent = normal * &tangent;
+
+                let mut occluded = 0.0;
+                for offset in &kernel {
+                    let sample = &position + (&tangent * offset.0 + &bitangent * offset.1 + normal * offset.2) * self.radius;
+                    let scene_depth = match sample_depth(target, &projection, &inverse_projection, &sample) {
+                        Some(depth) => depth,
+                        None => continue,
+                    };
+                    //View space looks down -z, so surfaces in front of the sample have a greater z
+                    if scene_depth >= sample.2 + self.bias {
+                        //Geometry far in front of the pixel is a different object, so it fades out
+                        let range = clamp_float(self.radius / (position.2 - scene_depth).abs(), 0.0, 1.0);
+                        occluded += range * range * (3.0 - 2.0 * range);
+                    }
+                }
+                occlusion[x + y * width] = (1.0 - occluded / kernel.len() as f32).powf(self.power);
+            }
+        }
+        self.blur(target, &occlusion)
+    }
+
+    //Darkens the colors of a frame buffer by its ambient occlusion
+    pub fn apply(&self, target: &mut FrameBuffer, camera: &Camera) {
+        let occlusion = self.occlusion(target, camera);
+        let width = target.width();
+        for (x, y, pixel) in target.color.enumerate_pixels_mut() {
+            let factor = occlusion[x as usize + y as usize * width];
+            for channel in pixel.0.iter_mut() {
+                *channel = (*channel as f32 * factor).round() as u8;
+            }
+        }
+    }
+
+    //Creates offsets in the hemisphere around +z, packed closer to the center where occlusion matters most
+    fn kernel(&self) -> Vec<Vec3f> {
+        (0..self.samples).map(|index| {
+            let seed = index as u32 * 3 + 1000;
+            let direction = Vec3f(random(seed) * 2.0 - 1.0, random(seed + 1) * 2.0 - 1.0, random(seed + 2)).normalize();
+            let scale = (index as f32 + 1.0) / self.samples as f32;
+            direction * (0.1 + 0.9 * scale * scale)
+        }).collect()
+    }
+
+    //Averages the occlusion of nearby covered pixels
+    fn blur(&self, target: &FrameBuffer, occlusion: &[f32]) -> Vec<f32> {
+        let (width, height) = (target.width(), target.height());
+        let radius = self.blur_radius as i64;
+        let mut blurred = occlusion.to_vec();
+        for y in 0..height {
+            for x in 0..width {
+                if !target.is_covered(x, y) {
+                    continue;
+                }
+                let mut total = 0.0;
+                let mut count = 0.0;
+                for offset_y in -radius..radius + 1 {
+                    for offset_x in -radius..radius + 1 {
+                        let (sample_x, sample_y) = (x as i64 + offset_x, y as i64 + offset_y);
+                        if sample_x < 0 || sample_y < 0 || sample_x >= width as i64 || sample_y >= height as i64 || !target.is_covered(sample_x as usize, sample_y as usize) {
+                            continue;
+                        }
+                        total += occlusion[sample_x as usize + sample_y as usize * width];
+                        count += 1.0;
+                    }
+                }
+                blurred[x + y * width] = total / count;
+            }
+        }
+        blurred
+    }
+}
+
+impl Default for Ssao {
+    fn default() -> Ssao {
+        Ssao::new()
+    }
+}
+
+//Reconstructs the view space position of the surface at a pixel from its depth
+fn view_position(target: &FrameBuffer, inverse_projection: &Mat4, x: usize, y: usize) -> Vec3f {
+    let depth = target.depth[x + y * target.width()];
+    //Undoes the viewport transform, which maps depth from [-1, 1] to [1, 0]
+    let ndc = Vec3f((x as f32 + 0.5) / target.width() as f32 * 2.0 - 1.0, (y as f32 + 0.5) / target.height() as f32 * 2.0 - 1.0, 1.0 - 2.0 * depth);
+    inverse_projection.transform_point(&ndc)
+}
+
+//Finds the view space z of the surface drawn where a view space point lands on the screen, or None if it is off the screen or empty
+fn sample_depth(target: &FrameBuffer, projection: &Mat4, inverse_projection: &Mat4, point: &Vec3f) -> Option<f32> {
+    let ndc = projection.transform_point(point);
+    let x = ((ndc.0 + 1.0) / 2.0 * target.width() as f32).floor();
+    let y = ((ndc.1 + 1.0) / 2.0 * target.height() as f32).floor();
+    if x < 0.0 || y < 0.0 || x >= target.width() as f32 || y >= target.height() as f32 || !target.is_covered(x as usize, y as usize) {
+        return None;
+    }
+    Some(view_position(target, inverse_projection, x as usize, y as usize).2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::light::*;
+    use crate::rendering::obj::*;
+    use crate::rendering::options::*;
+    use crate::rendering::shader::*;
+
+    //Renders a model with a camera looking at the origin, returning the occlusion of each pixel
+    fn render_occlusion(source: &str, eye: Vec3f) -> (FrameBuffer, Vec<f32>) {
+        let model = Model::from_reader(source.as_bytes()).unwrap();
+        let camera = Camera::perspective(eye, Vec3f(0.0, 0.0, 0.0), 60.0_f32.to_radians(), 1.0);
+        let mut target = FrameBuffer::new(64, 64);
+        let options = RenderOptions {
+            cull_mode: CullMode::None,
+            ..RenderOptions::new()
+        };
+        render_model(&model, &NormalShader, &camera, &Scene::new(), &options, &mut target);
+        let occlusion = Ssao::new().occlusion(&target, &camera);
+        (target, occlusion)
+    }
+
+    #[test]
+    fn flat_surfaces_are_not_occluded() {
+        let (target, occlusion) = render_occlusion("v -4 -4 0\nv 4 -4 0\nv 4 4 0\nv -4 4 0\nf 1 2 3 4\n", Vec3f(0.0, 0.0, 3.0));
+        for (index, value) in occlusion.iter().enumerate() {
+            assert!(target.is_covered(index % 64, index / 64));
+            assert!(*value > 0.99, "pixel {} has occlusion {}", index, value);
+        }
+    }
+
+    #[test]
+    fn creases_are_occluded() {
+        //A floor meeting a wall along the x axis, seen from in front of the crease
+        let (target, occlusion) = render_occlusion("v -4 0 0\nv 4 0 0\nv 4 0 4\nv -4 0 4\nv -4 0 0\nv 4 0 0\nv 4 4 0\nv -4 4 0\nf 1 2 3 4\nf 5 6 7 8\n", Vec3f(0.0, 2.0, 2.0));
+        let center = occlusion[32 + 32 * 64];
+        assert!(target.is_covered(32, 32));
+        assert!(center < 0.9, "the crease has occlusion {}", center);
+        //Pixels away from the crease stay open
+        assert!(occlusion[32 + 2 * 64] > center);
+        assert!(occlusion[32 + 61 * 64] > center);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,191 @@
+use crate::misc::utils::*;
+use crate::rendering::camera::*;
+use crate::rendering::framebuffer::*;
+use crate::rendering::light::*;
+use crate::rendering::obj::*;
+use crate::rendering::options::*;
+use crate::rendering::shader::*;
+
+//Renders a model into a frame buffer supersampling times larger in each direction, then filters it down into the target
+pub fn render_supersampled(model: &Model, shader: &dyn Shader, camera: &Camera, scene: &Scene, options: &RenderOptions, target: &mut FrameBuffer) {
+    let factor = options.supersampling as usize;
+    let mut large = upscale(target, factor);
+    let direct = RenderOptions {
+        supersampling: 1,
+        ..options.clone()
+    };
+    render_model(model, shader, camera, scene, &direct, &mut large);
+    downsample(&large, target, factor, options.downsample_filter);
+}
+
+//Copies a frame buffer into one factor times larger in each direction, so that new surfaces are depth tested and filtered against what was already drawn
+fn upscale(source: &FrameBuffer, factor: usize) -> FrameBuffer {
+    let width = source.width() * factor;
+    let height = source.height() * factor;
+    let count = source.sample_count();
+    let mut large = FrameBuffer::with_sample_positions(width as u32, height as u32, source.sample_positions.clone());
+    for y in 0..height {
+        for x in 0..width {
+            let (source_x, source_y) = (x / factor, y / factor);
+            let source_pixel = source_x + source_y * source.width();
+            large.depth[x + y * width] = source.depth[source_pixel];
+            large.normals[x + y * width] = source.normals[source_pixel].clone();
+            large.color.put_pixel(x as u32, y as u32, *source.color.get_pixel(source_x as u32, source_y as u32));
+
+            let start = large.sample_index(x, y);
+            let source_start = source.sample_index(source_x, source_y);
+            large.sample_depth[start..(start + count)].copy_from_slice(&source.sample_depth[source_start..(source_start + count)]);
+            large.sample_color[start..(start + count)].copy_from_slice(&source.sample_color[source_start..(source_start + count)]);
+            large.sample_coverage[start..(start + count)].copy_from_slice(&source.sample_coverage[source_start..(source_start + count)]);
+        }
+    }
+    large
+}
+
+//Finds the supersampled pixels and their normalized weights which make up each pixel along one axis
+fn filter_taps(size: usize, factor: usize, filter: DownsampleFilter) -> Vec<Vec<(usize, f32)>> {
+    let large_size = size * factor;
+    (0..size).map(|pixel| {
+        let center = pixel as f32 + 0.5;
+        let start = max_float(((center - filter.radius()) * factor as f32).floor(), 0.0) as usize;
+        let end = min_float(((center + filter.radius()) * factor as f32).ceil(), large_size as f32) as usize;
+        let mut taps: Vec<(usize, f32)> = (start..end)
+            .map(|sample| (sample, filter.weight((sample as f32 + 0.5) / factor as f32 - center)))
+            .filter(|(_, weight)| *weight != 0.0)
+            .collect();
+        //Weights are normalized so that filters clipped by the edge of the image keep their brightness
+        let total: f32 = taps.iter().map(|(_, weight)| weight).sum();
+        if total != 0.0 {
+            for tap in taps.iter_mut() {
+                tap.1 /= total;
+            }
+        }
+        taps
+    }).collect()
+}
+
+//Filters a supersampled frame buffer into the target, only changing pixels whose filter reaches a surface drawn into it
+fn downsample(large: &FrameBuffer, target: &mut FrameBuffer, factor: usize, filter: DownsampleFilter) {
+    let (width, height) = (target.width(), target.height());
+    let large_width = large.width();
+    let large_height = large.height();
+    let count = target.sample_count();
+
+    //Pixels which were drawn into no longer match the pixel they were copied from
+    let mut touched = vec![false; large_width * large_height];
+    for y in 0..large_height {
+        for x in 0..large_width {
+            let source_pixel = x / factor + y / factor * width;
+            touched[x + y * large_width] = large.depth[x + y * large_width] != target.depth[source_pixel]
+                || large.color.get_pixel(x as u32, y as u32).0 != target.color.get_pixel((x / factor) as u32, (y / factor) as u32).0;
+        }
+    }
+
+    //Fraction of each supersampled pixel which holds a final color, its resolved color is already multiplied by it
+    let coverage: Vec<f32> = (0..large_width * large_height).map(|pixel| large.sample_coverage[(pixel * count)..((pixel + 1) * count)].iter().sum::<f32>() / count as f32).collect();
+
+    //The filter is separable, so rows are filtered first and then columns
+    let columns = filter_taps(width, factor, filter);
+    let rows = filter_taps(height, factor, filter);
+    let mut horizontal = vec![([0.0f32; 3], 0.0f32, false); width * large_height];
+    for y in 0..large_height {
+        for (x, taps) in columns.iter().enumerate() {
+            let (sum, covered, changed) = &mut horizontal[x + y * width];
+            for (sample_x, weight) in taps {
+                let color = large.color.get_pixel(*sample_x as u32, y as u32).0;
+                for (channel, value) in sum.iter_mut().zip(color) {
+                    *channel += value as f32 * weight;
+                }
+                *covered += coverage[sample_x + y * large_width] * weight;
+                *changed |= touched[sample_x + y * large_width];
+            }
+        }
+    }
+
+    for (y, taps) in rows.iter().enumerate() {
+        for x in 0..width {
+            let mut sum = [0.0f32; 3];
+            let mut covered = 0.0;
+            let mut changed = false;
+            for (sample_y, weight) in taps {
+                let (row_sum, row_covered, row_changed) = &horizontal[x + sample_y * width];
+                for (channel, value) in sum.iter_mut().zip(row_sum) {
+                    *channel += value * weight;
+                }
+                covered += row_covered * weight;
+                changed |= *row_changed;
+            }
+            if !changed {
+                continue;
+            }
+
+            //Negative lobes of the filter may overshoot the range of a color, which can be no brighter than its coverage allows
+            let covered = clamp_float(covered, 0.0, 1.0);
+            let color = sum.map(|channel| clamp_float(channel.round(), 0.0, (255.0 * covered).round()) as u8);
+            //Uncovered parts are left for the background to fill, and samples with no surface inside them keep a depth of -infinity
+            let start = target.sample_index(x, y);
+            for sample in start..(start + count) {
+                target.sample_color[sample] = color;
+                target.sample_coverage[sample] = covered;
+            }
+
+            //Depth and normals come from the closest surface within the block of supersampled pixels
+            let mut closest = -f32::INFINITY;
+            for sample_y in (y * factor)..((y + 1) * factor) {
+                for sample_x in (x * factor)..((x + 1) * factor) {
+                    let large_start = large.sample_index(sample_x, sample_y);
+                    for index in 0..count {
+                        let depth = large.sample_depth[large_start + index];
+                        if depth > target.sample_depth[start + index] {
+                            target.sample_depth[start + index] = depth;
+                        }
+                    }
+                    if large.depth[sample_x + sample_y * large_width] > closest {
+                        closest = large.depth[sample_x + sample_y * large_width];
+                        target.normals[x + y * width] = large.normals[sample_x + sample_y * large_width].clone();
+                    }
+                }
+            }
+            target.resolve(x, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_weights_are_normalized() {
+        for filter in [DownsampleFilter::Box, DownsampleFilter::Tent, DownsampleFilter::Lanczos].iter() {
+            for factor in 1..5 {
+                let taps = filter_taps(7, factor, *filter);
+                assert_eq!(taps.len(), 7);
+                for (pixel, pixel_taps) in taps.iter().enumerate() {
+                    assert!(!pixel_taps.is_empty());
+                    //Pixels near the edges lose part of their filter, which the normalization makes up for
+                    let total: f32 = pixel_taps.iter().map(|(_, weight)| weight).sum();
+                    assert!((total - 1.0).abs() < 1e-5, "{:?} at {}x gives pixel {} a total weight of {}", filter, factor, pixel, total);
+                    assert!(pixel_taps.iter().all(|(sample, _)| *sample < 7 * factor));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn box_filters_average_the_block_of_each_pixel() {
+        let taps = filter_taps(3, 2, DownsampleFilter::Box);
+        assert_eq!(taps, vec![vec![(0, 0.5), (1, 0.5)], vec![(2, 0.5), (3, 0.5)], vec![(4, 0.5), (5, 0.5)]]);
+    }
+
+    #[test]
+    fn wider_filters_reach_neighboring_pixels_symmetrically() {
+        let taps = filter_taps(9, 2, DownsampleFilter::Tent);
+        let center = &taps[4];
+        let samples: Vec<usize> = center.iter().map(|(sample, _)| *sample).collect();
+        //The tent reaches half of the blocks of both neighbors
+        assert_eq!(samples, vec![7, 8, 9, 10]);
+        assert!((center[0].1 - center[3].1).abs() < 1e-6 && (center[1].1 - center[2].1).abs() < 1e-6);
+        assert!(center[1].1 > center[0].1);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,123 @@
+use crate::core::vector::*;
+use crate::rendering::obj::*;
+use std::collections::HashMap;
+
+//Finds the unnormalized tangent and bitangent of a face, which point along increasing u and v, or None if its texture coordinates have no area
+fn face_tangents(model: &Model, face_index: usize) -> Option<(Vec3f, Vec3f)> {
+    let face = &model.faces[face_index];
+    let v0 = &model.vertices[face[0].0];
+    let edge1 = &model.vertices[face[1].0] - v0;
+    let edge2 = &model.vertices[face[2].0] - v0;
+    let uv0 = model.uv(face_index, 0);
+    let uv1 = model.uv(face_index, 1);
+    let uv2 = model.uv(face_index, 2);
+    let (du1, dv1) = (uv1.0 - uv0.0, uv1.1 - uv0.1);
+    let (du2, dv2) = (uv2.0 - uv0.0, uv2.1 - uv0.1);
+
+    let determinant = du1 * dv2 - du2 * dv1;
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let r = 1.0 / determinant;
+    let tangent = (&edge1 * dv2 - &edge2 * dv1) * r;
+    let bitangent = (&edge2 * du1 - &edge1 * du2) * r;
+    Some((tangent, bitangent))
+}
+
+impl Model {
+    //Generates a tangent and bitangent for every corner of every face from the texture coordinates, which orient tangent space normal maps
+    pub fn generate_tangents(&mut self) {
+        //Sums the tangents of corners sharing a vertex, texture coordinate and normal
+        let mut sums: HashMap<(usize, usize, usize), (Vec3f, Vec3f)> = HashMap::new();
+        for face_index in 0..self.faces.len() {
+            let (tangent, bitangent) = match face_tangents(self, face_index) {
+                Some(tangents) => tangents,
+                None => continue,
+            };
+            for face_vertex in &self.faces[face_index] {
+                let sum = sums.entry((face_vertex.0, face_vertex.1, face_vertex.2)).or_insert((Vec3f(0.0, 0.0, 0.0), Vec3f(0.0, 0.0, 0.0)));
+                sum.0 = &sum.0 + &tangent;
+                sum.1 = &sum.1 + &bitangent;
+            }
+        }
+
+        let mut tangents = Vec::with_capacity(self.faces.len());
+        let mut bitangents = Vec::with_capacity(self.faces.len());
+        for face_index in 0..self.faces.len() {
+            let mut face_tangents = vec![];
+            let mut face_bitangents = vec![];
+            for corner in 0..3 {
+                let face_vertex = &self.faces[face_index][corner];
+                let normal = self.normal(face_index, corner);
+                let (tangent, bitangent) = match sums.get(&(face_vertex.0, face_vertex.1, face_vertex.2)) {
+                    Some(sum) => sum.clone(),
+                    None => (Vec3f(0.0, 0.0, 0.0), Vec3f(0.0, 0.0, 0.0)),
+                };
+
+                //Makes the tangent perpendicular to the normal, the bitangent keeps the handedness of the texture coordinates
+                let tangent = &tangent - &normal * Vec3f::dot(&normal, &tangent);
+                if Vec3f::magnitude(&tangent) < 1e-12 {
+                    face_tangents.push(Vec3f(0.0, 0.0, 0.0));
+                    face_bitangents.push(Vec3f(0.0, 0.0, 0.0));
+                    continue;
+                }
+                let tangent = tangent.normalize();
+                let perpendicular = &normal * &tangent;
+                let handedness = if Vec3f::dot(&perpendicular, &bitangent) < 0.0 { -1.0 } else { 1.0 };
+                face_tangents.push(tangent);
+                face_bitangents.push(perpendicular * handedness);
+            }
+            tangents.push(face_tangents);
+            bitangents.push(face_bitangents);
+        }
+        self.tangents = tangents;
+        self.bitangents = bitangents;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: &Vec3f, expected: &Vec3f) {
+        assert!(Vec3f::magnitude(&(actual - expected)) < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+
+    //A unit quad facing +z whose texture coordinates are given for each corner
+    fn quad(uv: &str) -> Model {
+        let source = format!("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n{}vn 0 0 1\nf 1/1/1 2/2/1 3/3/1 4/4/1\n", uv);
+        let mut model = Model::from_reader(source.as_bytes()).unwrap();
+        model.generate_tangents();
+        model
+    }
+
+    #[test]
+    fn tangents_follow_the_texture_coordinates() {
+        let model = quad("vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n");
+        for face_index in 0..model.faces.len() {
+            for corner in 0..3 {
+                assert_near(&model.tangents[face_index][corner], &Vec3f(1.0, 0.0, 0.0));
+                assert_near(&model.bitangents[face_index][corner], &Vec3f(0.0, 1.0, 0.0));
+            }
+        }
+
+        //Texture coordinates rotated a quarter turn rotate the tangent frame with them
+        let model = quad("vt 0 1\nvt 0 0\nvt 1 0\nvt 1 1\n");
+        assert_near(&model.tangents[0][0], &Vec3f(0.0, 1.0, 0.0));
+        assert_near(&model.bitangents[0][0], &Vec3f(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn mirrored_texture_coordinates_flip_the_bitangent() {
+        let model = quad("vt 1 0\nvt 0 0\nvt 0 1\nvt 1 1\n");
+        assert_near(&model.tangents[0][0], &Vec3f(-1.0, 0.0, 0.0));
+        assert_near(&model.bitangents[0][0], &Vec3f(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn faces_without_texture_area_have_no_tangents() {
+        let model = quad("vt 0.5 0.5\nvt 0.5 0.5\nvt 0.5 0.5\nvt 0.5 0.5\n");
+        assert_eq!(model.tangents[0][0], Vec3f(0.0, 0.0, 0.0));
+        assert_eq!(model.bitangents[0][0], Vec3f(0.0, 0.0, 0.0));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,348 @@
+use crate::core::vector::*;
+use crate::misc::utils::*;
+use image::{DynamicImage, ImageError};
+use std::cmp::{max, min};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+//Stores the pixels of one level of a mip chain as RGBA colors from 0 to 1, with v pointing up
+pub struct MipLevel {
+    pub width: usize,
+    pub height: usize,
+    pub texels: Vec<Vec4f>,
+}
+
+impl MipLevel {
+    pub fn texel(&self, x: usize, y: usize) -> &Vec4f {
+        &self.texels[x + y * self.width]
+    }
+
+    //Creates the next smaller level by averaging blocks of 2x2 texels
+    fn downsample(&self) -> MipLevel {
+        let width = max(self.width / 2, 1);
+        let height = max(self.height / 2, 1);
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                //Odd sizes reuse the last row or column of the larger level
+                let (x0, y0) = (min(2 * x, self.width - 1), min(2 * y, self.height - 1));
+                let (x1, y1) = (min(2 * x + 1, self.width - 1), min(2 * y + 1, self.height - 1));
+                let sum = self.texel(x0, y0) + self.texel(x1, y0) + self.texel(x0, y1) + self.texel(x1, y1);
+                texels.push(sum * 0.25);
+            }
+        }
+        MipLevel {
+            width,
+            height,
+            texels,
+        }
+    }
+}
+
+//Stores an image along with a chain of smaller copies of it, each half the size of the last
+pub struct Texture {
+    pub levels: Vec<MipLevel>,
+}
+
+impl Texture {
+    pub fn new(image: &DynamicImage) -> Texture {
+        //Images are stored top to bottom, so they are flipped to match texture coordinates
+        let image = image.flipv().to_rgba8();
+        let texels = image.pixels().map(|pixel| Vec4f(pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0, pixel[2] as f32 / 255.0, pixel[3] as f32 / 255.0)).collect();
+        let mut levels = vec![MipLevel {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            texels,
+        }];
+
+        //Halves the image until it is a single texel
+        loop {
+            let last = &levels[levels.len() - 1];
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+            let next = last.downsample();
+            levels.push(next);
+        }
+        Texture { levels }
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+}
+
+//An image used by the renderer, such as an environment or a cube map face, could not be loaded
+#[derive(Debug)]
+pub struct ImageLoadError {
+    pub path: PathBuf,
+    pub error: ImageError,
+}
+
+impl ImageLoadError {
+    pub fn new(path: &Path, error: ImageError) -> ImageLoadError {
+        ImageLoadError {
+            path: path.to_path_buf(),
+            error,
+        }
+    }
+}
+
+impl fmt::Display for ImageLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "could not load image {}: {}", self.path.display(), self.error)
+    }
+}
+
+impl Error for ImageLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+//Texture coordinate of a fragment along with how much it changes between neighboring pixels
+#[derive(Debug, PartialEq, Clone)]
+pub struct TexCoord {
+    pub uv: Vec2f,
+    //Change in the texture coordinate one pixel to the right
+    pub ddx: Vec2f,
+    //Change in the texture coordinate one pixel up
+    pub ddy: Vec2f,
+}
+
+impl TexCoord {
+    pub fn new(uv: Vec2f, ddx: Vec2f, ddy: Vec2f) -> TexCoord {
+        TexCoord { uv, ddx, ddy }
+    }
+
+    //Creates a texture coordinate with no derivatives, which always samples the largest mip level
+    pub fn point(uv: Vec2f) -> TexCoord {
+        TexCoord::new(uv, Vec2f(0.0, 0.0), Vec2f(0.0, 0.0))
+    }
+}
+
+//Controls how texture coordinates outside of [0, 1] are mapped onto the texture
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WrapMode {
+    //Tiles the texture
+    Repeat,
+    //Extends the pixels on the edges of the texture
+    ClampToEdge,
+    //Tiles the texture, flipping every other tile
+    MirroredRepeat,
+}
+
+impl WrapMode {
+    //Maps a pixel coordinate onto a texture of the given size
+    fn wrap(&self, coordinate: i64, size: usize) -> usize {
+        let size = size as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => coordinate.rem_euclid(size),
+            WrapMode::ClampToEdge => coordinate.clamp(0, size - 1),
+            WrapMode::MirroredRepeat => {
+                let mirrored = coordinate.rem_euclid(2 * size);
+                if mirrored < size { mirrored } else { 2 * size - 1 - mirrored }
+            }
+        };
+        wrapped as usize
+    }
+}
+
+//Controls how texels are combined when a texture is sampled
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Filter {
+    //Uses the closest texel
+    Nearest,
+    //Blends the four closest texels
+    Bilinear,
+}
+
+//Controls how mip levels are chosen when a texture is sampled
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MipmapFilter {
+    //Always uses the largest level
+    None,
+    //Uses the closest level
+    Nearest,
+    //Blends the two closest levels, which gives trilinear filtering along with bilinear filtering
+    Linear,
+}
+
+//Looks up the color of a texture at normalized texture coordinates
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sampler {
+    pub wrap_u: WrapMode,
+    pub wrap_v: WrapMode,
+    pub filter: Filter,
+    pub mipmap: MipmapFilter,
+    //Largest number of samples taken along a stretched footprint, 1 disables anisotropic filtering
+    pub max_anisotropy: u32,
+}
+
+impl Sampler {
+    pub fn new(wrap: WrapMode, filter: Filter) -> Sampler {
+        Sampler {
+            wrap_u: wrap,
+            wrap_v: wrap,
+            filter,
+            mipmap: MipmapFilter::Linear,
+            max_anisotropy: 1,
+        }
+    }
+
+    pub fn sample(&self, texture: &Texture, coordinate: &TexCoord) -> Vec4f {
+        //Measures the footprint of a pixel in texels of the largest level
+        let (width, height) = (texture.width() as f32, texture.height() as f32);
+        let ddx = Vec2f(coordinate.ddx.0 * width, coordinate.ddx.1 * height);
+        let ddy = Vec2f(coordinate.ddy.0 * width, coordinate.ddy.1 * height);
+        let length_x = Vec2f::magnitude(&ddx);
+        let length_y = Vec2f::magnitude(&ddy);
+        let (major, major_length, minor_length) = if length_x > length_y { (&coordinate.ddx, length_x, length_y) } else { (&coordinate.ddy, length_y, length_x) };
+
+        //Stretched footprints are covered by several samples along their major axis, each using a sharper level
+        let samples = if self.max_anisotropy > 1 && minor_length > 0.0 {
+            min_float((major_length / minor_length).ceil(), self.max_anisotropy as f32)
+        }
+        else {
+            1.0
+        };
+        let level = max_float((major_length / samples).log2(), 0.0);
+
+        if samples <= 1.0 {
+            return self.sample_level(texture, &coordinate.uv, level);
+        }
+        let mut color = Vec4f(0.0, 0.0, 0.0, 0.0);
+        for index in 0..samples as usize {
+            let offset = (index as f32 + 0.5) / samples - 0.5;
+            let uv = &coordinate.uv + major * offset;
+            color = color + self.sample_level(texture, &uv, level);
+        }
+        color * (1.0 / samples)
+    }
+
+    //Samples a texture at a fractional mip level
+    fn sample_level(&self, texture: &Texture, uv: &Vec2f, level: f32) -> Vec4f {
+        let last = texture.levels.len() - 1;
+        match self.mipmap {
+            MipmapFilter::None => self.sample_texels(&texture.levels[0], uv),
+            MipmapFilter::Nearest => self.sample_texels(&texture.levels[min(level.round() as usize, last)], uv),
+            MipmapFilter::Linear => {
+                let lower = min(level.floor() as usize, last);
+                let upper = min(lower + 1, last);
+                let t = level - level.floor();
+                let color = self.sample_texels(&texture.levels[lower], uv);
+                if lower == upper || t == 0.0 {
+                    color
+                }
+                else {
+                    color * (1.0 - t) + self.sample_texels(&texture.levels[upper], uv) * t
+                }
+            }
+        }
+    }
+
+    //Samples a single mip level
+    fn sample_texels(&self, level: &MipLevel, uv: &Vec2f) -> Vec4f {
+        //Converts the texture coordinate to pixels, where the centers of texels lie on half pixels
+        let x = uv.0 * level.width as f32 - 0.5;
+        let y = uv.1 * level.height as f32 - 0.5;
+        match self.filter {
+            Filter::Nearest => self.fetch(level, x.round() as i64, y.round() as i64).clone(),
+            Filter::Bilinear => bilinear(x, y, |x, y| self.fetch(level, x, y).clone()),
+        }
+    }
+
+    //Finds the texel at a pixel coordinate after wrapping
+    fn fetch<'a>(&self, level: &'a MipLevel, x: i64, y: i64) -> &'a Vec4f {
+        level.texel(self.wrap_u.wrap(x, level.width), self.wrap_v.wrap(y, level.height))
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Sampler {
+        Sampler::new(WrapMode::Repeat, Filter::Bilinear)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgba};
+
+    #[test]
+    fn repeat_tiles_coordinates() {
+        let wrapped: Vec<usize> = (-5..9).map(|coordinate| WrapMode::Repeat.wrap(coordinate, 4)).collect();
+        assert_eq!(wrapped, vec![3, 0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn clamp_to_edge_extends_the_edges() {
+        let wrapped: Vec<usize> = (-5..9).map(|coordinate| WrapMode::ClampToEdge.wrap(coordinate, 4)).collect();
+        assert_eq!(wrapped, vec![0, 0, 0, 0, 0, 0, 1, 2, 3, 3, 3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn mirrored_repeat_flips_every_other_tile() {
+        let wrapped: Vec<usize> = (-5..9).map(|coordinate| WrapMode::MirroredRepeat.wrap(coordinate, 4)).collect();
+        assert_eq!(wrapped, vec![3, 3, 2, 1, 0, 0, 1, 2, 3, 3, 2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn bilinear_filtering_blends_neighboring_texels() {
+        let texture = Texture {
+            levels: vec![MipLevel {
+                width: 2,
+                height: 1,
+                texels: vec![Vec4f(0.0, 0.0, 0.0, 1.0), Vec4f(1.0, 1.0, 1.0, 1.0)],
+            }],
+        };
+        let sampler = Sampler::new(WrapMode::ClampToEdge, Filter::Bilinear);
+        //Texel centers sample a single texel, halfway between them the texels are averaged
+        assert_eq!(sampler.sample(&texture, &TexCoord::point(Vec2f(0.25, 0.5))), Vec4f(0.0, 0.0, 0.0, 1.0));
+        assert_eq!(sampler.sample(&texture, &TexCoord::point(Vec2f(0.5, 0.5))), Vec4f(0.5, 0.5, 0.5, 1.0));
+        assert_eq!(sampler.sample(&texture, &TexCoord::point(Vec2f(0.9, 0.5))), Vec4f(1.0, 1.0, 1.0, 1.0));
+
+        let sampler = Sampler::new(WrapMode::Repeat, Filter::Bilinear);
+        assert_eq!(sampler.sample(&texture, &TexCoord::point(Vec2f(1.0, 0.5))), Vec4f(0.5, 0.5, 0.5, 1.0));
+    }
+
+    //Creates a checkerboard of black and white texels
+    fn checkerboard(width: u32, height: u32) -> Texture {
+        let image = ImageBuffer::from_fn(width, height, |x, y| if (x + y) % 2 == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) });
+        Texture::new(&DynamicImage::ImageRgba8(image))
+    }
+
+    #[test]
+    fn mip_chains_halve_down_to_one_texel() {
+        let texture = checkerboard(5, 3);
+        let sizes: Vec<(usize, usize)> = texture.levels.iter().map(|level| (level.width, level.height)).collect();
+        assert_eq!(sizes, vec![(5, 3), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn minified_samples_use_smaller_levels() {
+        let texture = checkerboard(8, 8);
+        let sampler = Sampler::new(WrapMode::Repeat, Filter::Bilinear);
+        //A footprint two texels wide falls on the level where each texel averages a 2x2 block of the checkerboard
+        let coordinate = TexCoord::new(Vec2f(0.3, 0.6), Vec2f(0.25, 0.0), Vec2f(0.0, 0.25));
+        let color = sampler.sample(&texture, &coordinate);
+        assert!((color.0 - 0.5).abs() < 1e-5 && (color.3 - 1.0).abs() < 1e-5, "{:?}", color);
+
+        //Without mipmapping the same footprint reads the full size checkerboard
+        let sampler = Sampler {
+            mipmap: MipmapFilter::None,
+            filter: Filter::Nearest,
+            ..Sampler::default()
+        };
+        for x in 0..8 {
+            let uv = Vec2f((x as f32 + 0.5) / 8.0, 0.5 / 8.0);
+            let color = sampler.sample(&texture, &TexCoord::new(uv, Vec2f(0.25, 0.0), Vec2f(0.0, 0.25)));
+            assert!(color.0 == 0.0 || color.0 == 1.0, "{:?}", color);
+        }
+    }
+}
\ No newline at end of file
@@ -1,14 +1,16 @@
 use crate::core::vector::*;
-use crate::rendering::obj::*;
+use crate::misc::utils::*;
+use crate::rendering::framebuffer::*;
+use crate::rendering::options::*;
+use crate::rendering::shader::*;
 use image::{ImageBuffer, Rgb};
-use std::cmp;
 
 //Converts a point to barycentric coordinates
-pub fn barycentric(x: f32, y: f32, points: &Vec<Vec3u>) -> Vec3f {
-    let u = Vec3f(points[2].get(0) as f32 - points[0].get(0) as f32, points[1].get(0) as f32 - points[0].get(0) as f32, points[0].get(0) as f32 - x) * Vec3f(points[2].get(1) as f32 - points[0].get(1) as f32, points[1].get(1) as f32 - points[0].get(1) as f32, points[0].get(1) as f32 - y);
-    
-    //If the absolute value of the z coordinate of Vec3 is negative, then the triangle is degenerate
-    if u.2.abs() < 1.0 {
+pub fn barycentric(x: f32, y: f32, points: &[Vec3f]) -> Vec3f {
+    let u = Vec3f(points[2].0 - points[0].0, points[1].0 - points[0].0, points[0].0 - x) * Vec3f(points[2].1 - points[0].1, points[1].1 - points[0].1, points[0].1 - y);
+
+    //If the z coordinate of u is close to zero the triangle has no area, so it is degenerate
+    if u.2.abs() < 1e-6 {
         Vec3f(-1.0, 1.0, 1.0)
     }
     else {
@@ -16,89 +18,250 @@ pub fn barycentric(x: f32, y: f32, points: &Vec<Vec3u>) -> Vec3f {
     }
 }
 
-//Draws a triangle on a canvas given its vertices
-pub fn draw_triangle(points: Vec<Vec3u>, zbuffer: &mut Vec<f32>, image: &mut ImageBuffer::<Rgb<u8>, Vec<u8>>, color: &[u8; 3]) {
-    let image_width = image.width() as usize;
-    let image_height = image.height() as usize;
+//Finds twice the signed area of a triangle on the screen, positive when its vertices wind counter clockwise
+pub fn signed_area(points: &[Vec3f]) -> f32 {
+    (points[1].0 - points[0].0) * (points[2].1 - points[0].1) - (points[2].0 - points[0].0) * (points[1].1 - points[0].1)
+}
 
+//Determines if a triangle on the screen is skipped by the cull mode
+pub fn is_culled(points: &[Vec3f], options: &RenderOptions) -> bool {
+    let winding = if signed_area(points) > 0.0 { Winding::CounterClockwise } else { Winding::Clockwise };
+    match options.cull_mode {
+        CullMode::Back => winding != options.front_face,
+        CullMode::Front => winding == options.front_face,
+        CullMode::None => false,
+    }
+}
+
+//Finds the pixels covered by the bounding box of a triangle clamped to the image, returns None if the box is off the image
+fn bounding_box(points: &[Vec3f], image_width: usize, image_height: usize) -> Option<(Vec2u, Vec2u)> {
+    sample_bounding_box(points, image_width, image_height, &[Vec2f(0.5, 0.5)])
+}
+
+//Finds the pixels whose samples may lie in the bounding box of a triangle, given the positions of the samples within a pixel
+fn sample_bounding_box(points: &[Vec3f], image_width: usize, image_height: usize, samples: &[Vec2f]) -> Option<(Vec2u, Vec2u)> {
     //Mutable min and max of the bounding box
-    let mut bounding_box_min = Vec2u(image_width - 1, image_height as usize - 1);
-    let mut bounding_box_max = Vec2u(0, 0);
-    
-    //Used to clamp the bounding box
-    let clamp = Vec2u(image_width - 1, image_height - 1);
+    let mut bounding_box_min = Vec2f(f32::INFINITY, f32::INFINITY);
+    let mut bounding_box_max = Vec2f(-f32::INFINITY, -f32::INFINITY);
 
     //Finds the minimum and maximum points of the triangle
-    for point in &points {
+    for point in points {
+        for index in 0..2 {
+            bounding_box_min.set(index, min_float(bounding_box_min.get(index), point.get(index)));
+            bounding_box_max.set(index, max_float(bounding_box_max.get(index), point.get(index)));
+        }
+    }
+
+    //The box is shrunk by the outermost samples before rounding, so pixels with no sample inside it are skipped
+    let mut sample_min = Vec2f(f32::INFINITY, f32::INFINITY);
+    let mut sample_max = Vec2f(-f32::INFINITY, -f32::INFINITY);
+    for sample in samples {
         for index in 0..2 {
-            bounding_box_min.set(index, cmp::max(0, cmp::min(bounding_box_min.get(index), point.get(index))));
-            bounding_box_max.set(index, cmp::min(clamp.get(index), cmp::max(bounding_box_max.get(index), point.get(index))));
+            sample_min.set(index, min_float(sample_min.get(index), sample.get(index)));
+            sample_max.set(index, max_float(sample_max.get(index), sample.get(index)));
         }
     }
+    let min_x = max_float((bounding_box_min.0 - sample_max.0).ceil(), 0.0);
+    let min_y = max_float((bounding_box_min.1 - sample_max.1).ceil(), 0.0);
+    let max_x = min_float((bounding_box_max.0 - sample_min.0).floor(), image_width as f32 - 1.0);
+    let max_y = min_float((bounding_box_max.1 - sample_min.1).floor(), image_height as f32 - 1.0);
+    if min_x > max_x || min_y > max_y {
+        return None;
+    }
+    Some((Vec2u(min_x as usize, min_y as usize), Vec2u(max_x as usize, max_y as usize)))
+}
+
+//Finds the z value of a point in a triangle
+fn interpolate_depth(points: &[Vec3f], barycentric_point: &Vec3f) -> f32 {
+    let mut z = 0.0;
+    for (index, point) in points.iter().enumerate() {
+        z += point.2 * barycentric_point.get(index);
+    }
+    z
+}
+
+//Draws a triangle on a canvas given its vertices
+pub fn draw_triangle(points: Vec<Vec3f>, zbuffer: &mut [f32], image: &mut ImageBuffer::<Rgb<u8>, Vec<u8>>, color: &[u8; 3]) {
+    let image_width = image.width() as usize;
+    let image_height = image.height() as usize;
+    let (bounding_box_min, bounding_box_max) = match bounding_box(&points, image_width, image_height) {
+        Some(bounds) => bounds,
+        None => return,
+    };
 
     //Loops through points in bounding box
     for x in (bounding_box_min.0)..(bounding_box_max.0 + 1) {
         for y in (bounding_box_min.1)..(bounding_box_max.1 + 1) {
-            let barycentric_point = barycentric(x as f32, y as f32, &points);
+            let barycentric_point = barycentric(x as f32 + 0.5, y as f32 + 0.5, &points);
             //If the barycentric point is negative the point is outside of the triangle
             if barycentric_point.0 < 0.0 || barycentric_point.1 < 0.0 || barycentric_point.2 < 0.0 {
                 continue;
             }
-            //Finds the z value of the current point
-            let mut z = 0.0;
-            for index in 0..3 {
-                z += points[index].get(2) as f32 * barycentric_point.get(index);
-            }
+            let z = interpolate_depth(&points, &barycentric_point);
 
             //Colors points in triangle if the z index is greater than the current z
-            if zbuffer[x as usize + y as usize * image_width] < z {
-                zbuffer[x as usize + y as usize * image_width] = z;
-                image.get_pixel_mut(x as u32, y as u32).0 = color.clone();
+            if zbuffer[x + y * image_width] < z {
+                zbuffer[x + y * image_width] = z;
+                image.get_pixel_mut(x as u32, y as u32).0 = *color;
             }
         }
     }
 }
 
-//Draws a triangle on a canvas given its vertices
-pub fn draw_triangle_model(points: Vec<Vec3u>, shader: &Shader, model: &Model, zbuffer: &mut Vec<f32>, image: &mut ImageBuffer::<Rgb<u8>, Vec<u8>>) {
-    let image_width = image.width() as usize;
-    let image_height = image.height() as usize;
-
-    //Mutable min and max of the bounding box
-    let mut bounding_box_min = Vec2u(image_width - 1, image_height - 1);
-    let mut bounding_box_max = Vec2u(0, 0);
-    
-    //Used to clamp the bounding box
-    let clamp = Vec2u(image_width - 1, image_height - 1);
+//Draws only the depth of a triangle given its vertices
+pub fn draw_triangle_depth(points: Vec<Vec3f>, zbuffer: &mut [f32], width: usize, height: usize) {
+    let (bounding_box_min, bounding_box_max) = match bounding_box(&points, width, height) {
+        Some(bounds) => bounds,
+        None => return,
+    };
 
-    //Finds the minimum and maximum points of the triangle
-    for point in &points {
-        for index in 0..2 {
-            bounding_box_min.set(index, cmp::max(0, cmp::min(bounding_box_min.get(index), point.get(index))));
-            bounding_box_max.set(index, cmp::min(clamp.get(index), cmp::max(bounding_box_max.get(index), point.get(index))));
+    //Loops through points in bounding box
+    for x in (bounding_box_min.0)..(bounding_box_max.0 + 1) {
+        for y in (bounding_box_min.1)..(bounding_box_max.1 + 1) {
+            let barycentric_point = barycentric(x as f32 + 0.5, y as f32 + 0.5, &points);
+            //If the barycentric point is negative the point is outside of the triangle
+            if barycentric_point.0 < 0.0 || barycentric_point.1 < 0.0 || barycentric_point.2 < 0.0 {
+                continue;
+            }
+            let z = interpolate_depth(&points, &barycentric_point);
+            if zbuffer[x + y * width] < z {
+                zbuffer[x + y * width] = z;
+            }
         }
     }
+}
+
+//Interpolates the varyings of a triangle at a point on the screen, which may lie outside of the triangle
+fn interpolate_at(points: &[Vec3f], vertices: &[VertexOutput], options: &RenderOptions, x: f32, y: f32) -> Vec<f32> {
+    let barycentric_point = barycentric(x, y, points);
+    let weights = match options.interpolation {
+        Interpolation::Perspective => perspective_weights(vertices, &barycentric_point),
+        Interpolation::Affine => barycentric_point,
+    };
+    interpolate_varyings(vertices, &weights)
+}
+
+//Draws a triangle into a frame buffer given its vertices and view space normal, coloring each pixel with a shader
+//Coverage and depth are tested at every sample of a pixel, but the shader runs once at the pixel center
+pub fn draw_triangle_model(points: Vec<Vec3f>, vertices: &[VertexOutput], face_index: usize, normal: &Vec3f, pipeline: &Pipeline, target: &mut FrameBuffer) {
+    let image_width = target.width();
+    let image_height = target.height();
+    let samples = target.sample_positions.clone();
+    let (bounding_box_min, bounding_box_max) = match sample_bounding_box(&points, image_width, image_height, &samples) {
+        Some(bounds) => bounds,
+        None => return,
+    };
+    let mut sample_depths = vec![-f32::INFINITY; samples.len()];
 
     //Loops through points in bounding box
     for x in (bounding_box_min.0)..(bounding_box_max.0 + 1) {
         for y in (bounding_box_min.1)..(bounding_box_max.1 + 1) {
-            let barycentric_point = barycentric(x as f32, y as f32, &points);
-            //If the barycentric point is negative the point is outside of the triangle
-            if barycentric_point.0 < 0.0 || barycentric_point.1 < 0.0 || barycentric_point.2 < 0.0 {
+            let first_sample = target.sample_index(x, y);
+            let mut covered = false;
+            for (index, sample) in samples.iter().enumerate() {
+                sample_depths[index] = -f32::INFINITY;
+                let barycentric_point = barycentric(x as f32 + sample.0, y as f32 + sample.1, &points);
+                //If the barycentric point is negative the sample is outside of the triangle
+                if barycentric_point.0 < 0.0 || barycentric_point.1 < 0.0 || barycentric_point.2 < 0.0 {
+                    continue;
+                }
+                let z = interpolate_depth(&points, &barycentric_point);
+                //Samples are only written if the z index is greater than their current z
+                if target.sample_depth[first_sample + index] < z {
+                    sample_depths[index] = z;
+                    covered = true;
+                }
+            }
+            if !covered {
                 continue;
             }
-            //Finds the z value of the current point
-            let mut z = 0.0;
-            for index in 0..3 {
-                z += points[index].get(2) as f32 * barycentric_point.get(index);
+
+            //The pixel center may lie outside of the triangle when only some samples are covered
+            let z = interpolate_depth(&points, &barycentric(x as f32 + 0.5, y as f32 + 0.5, &points));
+            let varyings = interpolate_at(&points, vertices, pipeline.options, x as f32 + 0.5, y as f32 + 0.5);
+            //Derivatives are found by extending the interpolation to the neighboring pixels
+            let right = interpolate_at(&points, vertices, pipeline.options, x as f32 + 1.5, y as f32 + 0.5);
+            let up = interpolate_at(&points, vertices, pipeline.options, x as f32 + 0.5, y as f32 + 1.5);
+            let fragment = Fragment {
+                face_index,
+                x,
+                y,
+                depth: z,
+                ddx: right.iter().zip(&varyings).map(|(next, current)| next - current).collect(),
+                ddy: up.iter().zip(&varyings).map(|(next, current)| next - current).collect(),
+                varyings,
+            };
+            //Discarded fragments leave the z buffer untouched
+            if let Some(color) = pipeline.shader.fragment(pipeline.model, pipeline.uniforms, &fragment) {
+                for (index, depth) in sample_depths.iter().enumerate() {
+                    if *depth > -f32::INFINITY {
+                        target.sample_depth[first_sample + index] = *depth;
+                        target.sample_color[first_sample + index] = color;
+                        target.sample_coverage[first_sample + index] = 1.0;
+                    }
+                }
+                target.normals[x + y * image_width] = normal.clone();
+                target.resolve(x, y);
             }
+        }
+    }
+}
 
-            //Colors points in triangle if the z index is greater than the current z
-            if zbuffer[x + y * image_width] < z {
-                let color = shader.compute_color(&barycentric_point, model);
-                zbuffer[x + y * image_width] = z;
-                image.get_pixel_mut(x as u32, y as u32).0 = color;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn barycentric_weights_match_vertices() {
+        let points = vec![Vec3f(0.5, 0.5, 0.0), Vec3f(3.5, 1.0, 0.0), Vec3f(1.0, 3.25, 0.0)];
+        for (index, point) in points.iter().enumerate() {
+            let weights = barycentric(point.0, point.1, &points);
+            for weight_index in 0..3 {
+                let expected = if weight_index == index { 1.0 } else { 0.0 };
+                assert!((weights.get(weight_index) - expected).abs() < 1e-5, "{:?}", weights);
             }
         }
     }
+
+    #[test]
+    fn triangles_cover_pixel_centers() {
+        let mut image = ImageBuffer::<Rgb<u8>, Vec<u8>>::new(4, 4);
+        let mut zbuffer = vec![-f32::INFINITY; 16];
+        //The hypotenuse passes through the centers of the pixels where x + y is 3
+        draw_triangle(vec![Vec3f(0.0, 0.0, 0.0), Vec3f(4.0, 0.0, 0.0), Vec3f(0.0, 4.0, 0.0)], &mut zbuffer, &mut image, &[255, 255, 255]);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            assert_eq!(pixel.0[0] == 255, x + y <= 3, "pixel ({}, {})", x, y);
+        }
+
+        //Moving the triangle by less than a pixel uncovers the centers it no longer contains
+        let mut image = ImageBuffer::<Rgb<u8>, Vec<u8>>::new(4, 4);
+        let mut zbuffer = vec![-f32::INFINITY; 16];
+        draw_triangle(vec![Vec3f(0.75, 0.0, 0.0), Vec3f(4.75, 0.0, 0.0), Vec3f(0.75, 4.0, 0.0)], &mut zbuffer, &mut image, &[255, 255, 255]);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            assert_eq!(pixel.0[0] == 255, x >= 1 && x + y <= 3, "pixel ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn culling_depends_on_mode_and_front_face() {
+        let counter_clockwise = vec![Vec3f(0.0, 0.0, 0.0), Vec3f(1.0, 0.0, 0.0), Vec3f(0.0, 1.0, 0.0)];
+        let clockwise = vec![Vec3f(0.0, 0.0, 0.0), Vec3f(0.0, 1.0, 0.0), Vec3f(1.0, 0.0, 0.0)];
+        let mut options = RenderOptions::new();
+        for front_face in [Winding::CounterClockwise, Winding::Clockwise].iter() {
+            options.front_face = *front_face;
+            let (front, back) = if *front_face == Winding::CounterClockwise { (&counter_clockwise, &clockwise) } else { (&clockwise, &counter_clockwise) };
+
+            options.cull_mode = CullMode::Back;
+            assert!(!is_culled(front, &options));
+            assert!(is_culled(back, &options));
+
+            options.cull_mode = CullMode::Front;
+            assert!(is_culled(front, &options));
+            assert!(!is_culled(back, &options));
+
+            options.cull_mode = CullMode::None;
+            assert!(!is_culled(front, &options));
+            assert!(!is_culled(back, &options));
+        }
+    }
 }
@@ -0,0 +1,200 @@
+use crate::core::vector::*;
+
+//Finds the normal of a polygon with Newell's method, which also works for concave and non planar polygons
+fn newell_normal(points: &[Vec3f]) -> Vec3f {
+    let mut normal = Vec3f(0.0, 0.0, 0.0);
+    for index in 0..points.len() {
+        let current = &points[index];
+        let next = &points[(index + 1) % points.len()];
+        normal.0 += (current.1 - next.1) * (current.2 + next.2);
+        normal.1 += (current.2 - next.2) * (current.0 + next.0);
+        normal.2 += (current.0 - next.0) * (current.1 + next.1);
+    }
+    normal
+}
+
+//Projects a polygon onto the axis aligned plane closest to its own plane, keeping its winding counter clockwise
+fn project(points: &[Vec3f]) -> Vec<Vec2f> {
+    let normal = newell_normal(points);
+    let (x, y, z) = (normal.0.abs(), normal.1.abs(), normal.2.abs());
+
+    //Drops the axis along which the polygon is largest when viewed
+    let (axis0, axis1, flip) = if z >= x && z >= y {
+        (0, 1, normal.2 < 0.0)
+    }
+    else if y >= x {
+        (2, 0, normal.1 < 0.0)
+    }
+    else {
+        (1, 2, normal.0 < 0.0)
+    };
+
+    points.iter().map(|point| {
+        if flip {
+            Vec2f(point.get(axis1), point.get(axis0))
+        }
+        else {
+            Vec2f(point.get(axis0), point.get(axis1))
+        }
+    }).collect()
+}
+
+//Finds twice the signed area of a 2D triangle, positive when it winds counter clockwise
+fn cross(a: &Vec2f, b: &Vec2f, c: &Vec2f) -> f32 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+//Determines if a point lies inside or on the edge of a counter clockwise 2D triangle
+fn in_triangle(point: &Vec2f, a: &Vec2f, b: &Vec2f, c: &Vec2f) -> bool {
+    cross(a, b, point) >= 0.0 && cross(b, c, point) >= 0.0 && cross(c, a, point) >= 0.0
+}
+
+//Splits a polygon into a fan of triangles around its first vertex
+fn fan(count: usize) -> Vec<[usize; 3]> {
+    (1..count - 1).map(|index| [0, index, index + 1]).collect()
+}
+
+//Splits a polygon into triangles with ear clipping
+fn ear_clip(points: &[Vec2f]) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&index| {
+            let prev = &points[remaining[(index + count - 1) % count]];
+            let current = &points[remaining[index]];
+            let next = &points[remaining[(index + 1) % count]];
+
+            //Reflex vertices can not be ears
+            if cross(prev, current, next) <= 0.0 {
+                return false;
+            }
+            //An ear may not contain any other vertex of the polygon
+            !remaining.iter().any(|&other| {
+                let point = &points[other];
+                point != prev && point != current && point != next && in_triangle(point, prev, current, next)
+            })
+        });
+
+        match ear {
+            Some(index) => {
+                triangles.push([remaining[(index + count - 1) % count], remaining[index], remaining[(index + 1) % count]]);
+                remaining.remove(index);
+            }
+            //Self intersecting polygons may have no ears left, so the rest of the polygon is fanned
+            None => {
+                triangles.extend(fan(count).iter().map(|triangle| [remaining[triangle[0]], remaining[triangle[1]], remaining[triangle[2]]]));
+                return triangles;
+            }
+        }
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+//Splits a polygon into triangles, returning the indices of their vertices in the winding of the polygon
+pub fn triangulate(points: &[Vec3f]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return vec![];
+    }
+    if points.len() == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    //Convex polygons are fanned, concave polygons are ear clipped
+    let projected = project(points);
+    let count = projected.len();
+    let convex = (0..count).all(|index| cross(&projected[(index + count - 1) % count], &projected[index], &projected[(index + 1) % count]) >= 0.0);
+    if convex {
+        fan(count)
+    }
+    else {
+        ear_clip(&projected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //An L shaped hexagon, which is concave at its fifth vertex when wound counter clockwise
+    fn l_shape() -> Vec<Vec3f> {
+        vec![
+            Vec3f(0.0, 0.0, 0.0),
+            Vec3f(2.0, 0.0, 0.0),
+            Vec3f(2.0, 1.0, 0.0),
+            Vec3f(1.0, 1.0, 0.0),
+            Vec3f(1.0, 2.0, 0.0),
+            Vec3f(0.0, 2.0, 0.0),
+        ]
+    }
+
+    //An arrow head, which is concave at its notch
+    fn arrow() -> Vec<Vec3f> {
+        vec![
+            Vec3f(0.0, 0.0, 0.0),
+            Vec3f(2.0, 1.0, 0.0),
+            Vec3f(0.0, 2.0, 0.0),
+            Vec3f(0.5, 1.0, 0.0),
+        ]
+    }
+
+    //Checks that the triangles exactly cover the polygon and each keeps its winding
+    fn assert_covers(points: &[Vec3f], triangles: &[[usize; 3]]) {
+        assert_eq!(triangles.len(), points.len() - 2);
+        let polygon_normal = newell_normal(points);
+        let mut total = Vec3f(0.0, 0.0, 0.0);
+        for triangle in triangles {
+            let (a, b, c) = (&points[triangle[0]], &points[triangle[1]], &points[triangle[2]]);
+            //Twice the area of the triangle along its normal
+            let normal = (b - a) * (c - a);
+            assert!(Vec3f::dot(&normal, &polygon_normal) > 0.0, "triangle {:?} is flipped", triangle);
+            total = total + normal;
+        }
+        assert!(Vec3f::magnitude(&(total - polygon_normal)) < 1e-4);
+    }
+
+    #[test]
+    fn triangles_are_kept() {
+        assert_eq!(triangulate(&l_shape()[0..3]), vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn convex_polygons_are_fanned() {
+        let square = vec![Vec3f(0.0, 0.0, 0.0), Vec3f(1.0, 0.0, 0.0), Vec3f(1.0, 1.0, 0.0), Vec3f(0.0, 1.0, 0.0)];
+        assert_eq!(triangulate(&square), vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn concave_polygons_are_covered_counter_clockwise() {
+        for polygon in [l_shape(), arrow()] {
+            assert_covers(&polygon, &triangulate(&polygon));
+        }
+    }
+
+    #[test]
+    fn concave_polygons_are_covered_clockwise() {
+        for mut polygon in [l_shape(), arrow()] {
+            polygon.reverse();
+            assert_covers(&polygon, &triangulate(&polygon));
+        }
+    }
+
+    #[test]
+    fn concave_polygons_off_the_xy_plane_are_covered() {
+        //Swaps y and z so the polygon lies in the xz plane, in both windings
+        let mut polygon: Vec<Vec3f> = l_shape().iter().map(|point| Vec3f(point.0, point.2, point.1)).collect();
+        assert_covers(&polygon, &triangulate(&polygon));
+        polygon.reverse();
+        assert_covers(&polygon, &triangulate(&polygon));
+    }
+
+    #[test]
+    fn concave_polygons_starting_at_a_reflex_vertex_are_covered() {
+        //Starting at the reflex vertex, a fan would cover area outside of the polygon, so ear clipping must be used
+        let mut polygon = arrow();
+        polygon.rotate_left(3);
+        assert_covers(&polygon, &triangulate(&polygon));
+    }
+}
\ No newline at end of file